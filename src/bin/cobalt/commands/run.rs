@@ -3,6 +3,8 @@ use argh::FromArgs;
 use std::path::PathBuf;
 use std::env;
 
+use cobalt_lang::errors::VmError;
+use cobalt_lang::interpreter::io::FakeClock;
 use cobalt_lang::interpreter::vm;
 use cobalt_lang::utils::files_u8;
 
@@ -13,9 +15,30 @@ pub struct Run {
     #[argh(positional)]
     /// the name of the file to be interpreted
     pub file: String,
+    #[argh(positional)]
+    /// the arguments the program gets from args(), put them after -- (cobalt run app.cbx -- a b c)
+    pub args: Vec<String>,
     #[argh(switch)]
     /// whether to enable or disable debug mode, which provides detailed information
-    pub debug: bool
+    pub debug: bool,
+    #[argh(option, long = "allow-read")]
+    /// let the program read files inside this directory with the fs module, can be given more than once
+    pub allow_read: Vec<String>,
+    #[argh(option, long = "allow-write")]
+    /// let the program write files inside this directory with the fs module, can be given more than once
+    pub allow_write: Vec<String>,
+    #[argh(switch, long = "fake-time")]
+    /// give the time module a fake clock that starts at the Unix epoch and only moves when the program sleeps, so every run sees the same times
+    pub fake_time: bool,
+    #[argh(option)]
+    /// seed the random module with this number, so every run gives the same random numbers
+    pub seed: Option<u64>,
+    #[argh(option, long = "schedule-seed")]
+    /// run spawned tasks in a random order picked with this seed instead of taking turns, every run with the same seed gets the same order
+    pub schedule_seed: Option<u64>,
+    #[argh(switch, long = "allow-env")]
+    /// let the program read environment variables with env(name)
+    pub allow_env: bool
 }
 
 pub fn run(args: Run) {
@@ -26,5 +49,41 @@ pub fn run(args: Run) {
 
     let bytecode: Vec<u8> = files_u8::read_file_to_vec(file_path.to_str().expect("Error: Failed to convert working directory into a string."));
     let mut vm = vm::VM::new(bytecode, args.debug);
-    vm.interpret();
+
+    for dir in &args.allow_read {
+        if let Err(err) = vm.allow_read(dir) {
+            eprintln!("Error: Can't allow reading '{}': {}", dir, err);
+            std::process::exit(1);
+        }
+    }
+    for dir in &args.allow_write {
+        if let Err(err) = vm.allow_write(dir) {
+            eprintln!("Error: Can't allow writing '{}': {}", dir, err);
+            std::process::exit(1);
+        }
+    }
+
+    if args.fake_time {
+        vm.set_clock(FakeClock::default());
+    }
+
+    vm.set_args(args.args);
+    if args.allow_env {
+        vm.allow_env();
+    }
+    if let Some(seed) = args.seed {
+        vm.set_seed(seed);
+    }
+    if let Some(seed) = args.schedule_seed {
+        vm.set_schedule_seed(seed);
+    }
+
+    match vm.interpret() {
+        Ok(_) => {}
+        Err(VmError::Exit { code }) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(err.exit_code());
+        }
+    }
 }
\ No newline at end of file
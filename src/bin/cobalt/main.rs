@@ -16,8 +16,51 @@ enum Command {
     Version(commands::version::Version)
 }
 
+// argh only understands --allow-read DIR, so --allow-read=DIR is split into two arguments first
+// everything after -- is left alone, those are the program's own arguments
+fn split_equals(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut program_args = false;
+    for arg in args {
+        if program_args {
+            split.push(arg);
+            continue;
+        }
+        program_args = arg == "--";
+
+        match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                split.push(flag.to_string());
+                split.push(value.to_string());
+            }
+            _ => split.push(arg),
+        }
+    }
+    split
+}
+
+// argh::from_env, with the arguments going through split_equals
+fn parse_args() -> TopLevel {
+    let args = split_equals(std::env::args());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = args.first().copied().unwrap_or("cobalt");
+
+    TopLevel::from_args(&[command], args.get(1..).unwrap_or_default()).unwrap_or_else(|early_exit| {
+        match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                std::process::exit(0);
+            }
+            Err(()) => {
+                eprintln!("{}\nRun {} --help for more information.", early_exit.output, command);
+                std::process::exit(1);
+            }
+        }
+    })
+}
+
 fn main() {
-    let command: TopLevel = argh::from_env();
+    let command = parse_args();
 
     match command.nested {
         Command::Run(run) => {
@@ -5,8 +5,10 @@ use std::{env, process};
 
 use cobalt_lang::lexer::lexer;
 use cobalt_lang::parser::parser;
+use cobalt_lang::typechecker::checker;
 use cobalt_lang::codegen::generator;
 use cobalt_lang::utils::files_u8;
+use cobalt_lang::errors::{self, Diagnostic};
 
 #[derive(FromArgs, Debug)]
 #[argh(subcommand, name = "build")]
@@ -34,18 +36,18 @@ pub fn run(args: Build) {
         process::exit(1); 
     });
 
-    let output_file_name: String = if args.output.is_none() {
+    let output_file_name: String = if let Some(output) = args.output {
+        output
+    } else {
         files_u8::get_file_name_without_extension(&args.file).unwrap_or_else(|| {
             eprintln!("Error: Failed to extract file name from file positional argument. Try specifying the -o/--output flag.");
             process::exit(1);
         })
-    } else {
-        args.output.unwrap()
     };
 
 
     let mut lexer_ = lexer::Lexer::new(file_content.chars().collect());
-    let tokens = lexer_.lex();
+    let tokens = lexer_.lex().unwrap_or_else(|err| fail(&err));
 
     if args.debug {
         for token in &tokens {
@@ -54,20 +56,46 @@ pub fn run(args: Build) {
     }
 
     let mut parser_ = parser::Parser::new(tokens);
-    let ast = parser_.produce_ast();
+    let mut ast = parser_.produce_ast().unwrap_or_else(|err| fail(&err));
+
+    let mut checker_ = checker::Checker::new();
+    let checked = checker_.check(&mut ast.body);
+    report(checker_.warnings());
+    checked.unwrap_or_else(|err| fail(&err));
 
     if args.debug {
         for node in &ast.body {
             println!("{:#?}", node);
         }
+
+        for (ident, r#type) in checker_.inferred() {
+            println!("Inferred: {}: {}", ident, r#type);
+        }
+
+        for test in checker_.tests() {
+            println!("Test: {}", test);
+        }
     }
 
     let mut codegen_ = generator::Codegen::new();
-    let bytecode = codegen_.generate(ast.body);
+    let generated = codegen_.generate(ast.body).cloned();
+    report(codegen_.warnings());
+    let bytecode = generated.unwrap_or_else(|err| fail(&err));
 
     if args.debug {
         println!("{:?}", bytecode);
     }
 
-    files_u8::write_vec_to_file(file_path.join(output_file_name + ".cbx").to_str().expect("Error: Failed to convert working directory into a string."), bytecode);
+    files_u8::write_vec_to_file(file_path.join(output_file_name + ".cbx").to_str().expect("Error: Failed to convert working directory into a string."), &bytecode);
+}
+
+fn report(warnings: &[Diagnostic]) {
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+}
+
+fn fail(err: &Diagnostic) -> ! {
+    eprintln!("{}", err);
+    process::exit(errors::EXIT_COMPILE_ERROR);
 }
\ No newline at end of file
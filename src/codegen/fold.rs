@@ -0,0 +1,75 @@
+// works out expressions made of literals and constants while generating, so the VM only sees their value
+// every operation gives the same result the VM would give, and the ones the VM would stop the program for (like an int overflowing) are errors here instead
+
+use crate::errors::{Diagnostic, Stage};
+
+/// A value that is known at compile time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+/// What folding found out about an expression, so generating it doesn't have to fold the operations in it again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Folded {
+    Constant(Constant), // the whole expression is known
+    Binary(Box<Folded>, Box<Folded>), // an operation that is done at runtime, with what was found out about its operands
+    Unary(Box<Folded>),
+    Logical(Box<Folded>, Box<Folded>),
+    Runtime, // anything else, its value is only known at runtime
+}
+
+/// The value of a binary operation on two constants, `None` when it has to be left for the VM.
+///
+/// Dividing an int by zero and int overflow are errors here instead of at runtime.
+pub fn binary(operator: &str, left: &Constant, right: &Constant) -> Result<Option<Constant>, Diagnostic> {
+    use Constant::*;
+
+    Ok(match (operator, left, right) {
+        ("/", Int(_), Int(0)) => return Err(Diagnostic::error(Stage::Generator, "Cannot divide by zero.")),
+        ("%", Int(_), Int(0)) => return Err(Diagnostic::error(Stage::Generator, "Cannot perform modulus by zero.")),
+        ("+", Int(l), Int(r)) => Some(Int(l.checked_add(*r).ok_or_else(|| overflow("addition"))?)),
+        ("-", Int(l), Int(r)) => Some(Int(l.checked_sub(*r).ok_or_else(|| overflow("subtraction"))?)),
+        ("*", Int(l), Int(r)) => Some(Int(l.checked_mul(*r).ok_or_else(|| overflow("multiplication"))?)),
+        ("/", Int(l), Int(r)) => Some(Int(l.checked_div(*r).ok_or_else(|| overflow("division"))?)),
+        ("%", Int(l), Int(r)) => Some(Int(l.checked_rem(*r).ok_or_else(|| overflow("modulus"))?)),
+        ("+", Float(l), Float(r)) => Some(Float(l + r)),
+        ("-", Float(l), Float(r)) => Some(Float(l - r)),
+        ("*", Float(l), Float(r)) => Some(Float(l * r)),
+        ("/", Float(l), Float(r)) => Some(Float(l / r)),
+        // strings are written into the bytecode, so they can only be as long as a string literal
+        ("+", Str(l), Str(r)) if l.len() + r.len() <= u8::MAX as usize => Some(Str(format!("{}{}", l, r))),
+        ("==", l, r) => Some(Bool(l == r)),
+        ("!=", l, r) => Some(Bool(l != r)),
+        ("<", Int(l), Int(r)) => Some(Bool(l < r)),
+        (">", Int(l), Int(r)) => Some(Bool(l > r)),
+        ("<=", Int(l), Int(r)) => Some(Bool(l <= r)),
+        (">=", Int(l), Int(r)) => Some(Bool(l >= r)),
+        ("<", Float(l), Float(r)) => Some(Bool(l < r)),
+        (">", Float(l), Float(r)) => Some(Bool(l > r)),
+        ("<=", Float(l), Float(r)) => Some(Bool(l <= r)),
+        (">=", Float(l), Float(r)) => Some(Bool(l >= r)),
+        _ => None,
+    })
+}
+
+/// The value of a unary operation on a constant, `None` when it has to be left for the VM.
+///
+/// Negating the smallest int is an error here instead of at runtime.
+pub fn unary(operator: &str, value: &Constant) -> Result<Option<Constant>, Diagnostic> {
+    Ok(match (operator, value) {
+        ("+", Constant::Int(_) | Constant::Float(_)) => Some(value.clone()),
+        ("-", Constant::Int(value)) => Some(Constant::Int(value.checked_neg().ok_or_else(|| overflow("negation"))?)),
+        ("-", Constant::Float(value)) => Some(Constant::Float(-value)),
+        ("!", Constant::Bool(value)) => Some(Constant::Bool(!value)),
+        _ => None,
+    })
+}
+
+// the same message the VM gives
+fn overflow(operation: &str) -> Diagnostic {
+    Diagnostic::error(Stage::Generator, format!("Integer overflow in {}.", operation))
+}
@@ -3,20 +3,32 @@
 // a hashmap that maps variable names to their memory stack numbers
 
 use std::collections::HashMap;
-use std::process;
+use crate::errors::{Diagnostic, Stage};
 use crate::interpreter::constants;
 use crate::parser::ast;
+use crate::stdlib::{self, Member};
+use crate::typechecker::types::Type;
+use super::fold::{self, Constant, Folded};
 
 pub struct Codegen {
     bytecode: Vec<u8>,
     scopes: Vec<HashMap<String, Variable>>,
     next_var_id: usize, // used to map variable names (in AST) to their IDs (in bytecode, which doesn't support string names)
-    labels: HashMap<String, usize>, // used to map functions to their IP (instruction pointer), aka the byte they start at
+    externs: HashMap<String, bool>, // extern functions and whether they return a value, they are called by name since the host registers them by name
+    lambda_depth: usize, // how many lambdas the code being generated is inside of, 0 at the top level
+    depth: usize, // how deeply the statement or expression being generated is nested, see nest
+    local_counts: Vec<usize>, // how many locals each lambda being generated uses, its parameters and then the variables its block declares
+    deferred: Vec<Vec<(ast::Expr, HashMap<String, Variable>)>>, // for every block being generated, what it deferred along with its scope at that point
+    warnings: Vec<Diagnostic>,
 }
 
+#[derive(Clone)]
 pub struct Variable {
     pub constant: bool,
-    pub id: usize
+    pub id: usize,
+    pub used: bool, // whether the variable has been read, used to lint shadowing that throws away a value
+    pub lambda: Option<usize>, // for lambda parameters and variables in lambda blocks, the depth of the lambda they belong to and id is their local slot, other variables live in global slots
+    pub value: Option<Constant>, // for constants whose value is known at compile time, every use is replaced with it and they don't have a slot
 }
 
 impl Default for Codegen {
@@ -31,57 +43,195 @@ impl Codegen {
             bytecode: Vec::new(),
             scopes: vec![HashMap::new()],
             next_var_id: 0,
-            labels: HashMap::new()
+            externs: HashMap::new(),
+            lambda_depth: 0,
+            depth: 0,
+            local_counts: Vec::new(),
+            deferred: Vec::new(),
+            warnings: Vec::new()
         }
     }
 
+    // the parser already limits how deeply code is nested, this keeps an AST that was built some other way from running out of stack
+    fn nest(&mut self) -> Result<(), Diagnostic> {
+        if self.depth == ast::MAX_DEPTH {
+            return Err(Diagnostic::error(Stage::Generator, format!("The code is nested too deeply, it can be at most {} levels deep.", ast::MAX_DEPTH)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     fn emit_u64(&self, value: u64) -> Vec<u8> {
         value.to_le_bytes().to_vec()
     }
 
     // emits it in the form PUSH_STR expects, which is <the amount of bytes that are string> <the string bytes>
-    fn emit_str(&self, value: &str) -> Vec<u8> {
+    fn emit_str(&self, value: &str) -> Result<Vec<u8>, Diagnostic> {
         let bytes = value.as_bytes();
         let length = bytes.len();
 
-        assert!(length <= u8::MAX as usize, "Generator Error: String too long to encode length in one byte");
+        if length > u8::MAX as usize {
+            return Err(Diagnostic::error(Stage::Generator, format!("String is {} bytes long, but strings can be at most {} bytes.", length, u8::MAX)));
+        }
 
         let mut result = Vec::with_capacity(1 + length);
         result.push(length as u8);
         result.extend_from_slice(bytes);
 
-        result
+        Ok(result)
+    }
+
+    // a slot for a new variable, inside a lambda (one with a block, the only ones with declarations) it's the next local, elsewhere the next global
+    fn next_slot(&mut self) -> (usize, Option<usize>) {
+        match self.local_counts.last_mut() {
+            Some(count) => {
+                *count += 1;
+                (*count - 1, Some(self.lambda_depth))
+            }
+            None => {
+                self.next_var_id += 1;
+                (self.next_var_id - 1, None)
+            }
+        }
+    }
+
+    fn emit_store(&mut self, id: usize, lambda: Option<usize>) {
+        // STORE <slot> or STORE_LOCAL <slot>
+        self.bytecode.push(if lambda.is_some() { constants::STORE_LOCAL } else { constants::STORE });
+        self.bytecode.extend(self.emit_u64(id as u64));
     }
 
     fn set_var(&mut self, ident: &String, constant: bool) {
+        let (id, lambda) = self.next_slot();
+        self.insert_var(ident, Variable { constant, id, used: false, lambda, value: None });
+    }
+
+    // a constant that is replaced with its value wherever it's used, so it doesn't need a slot
+    fn set_const(&mut self, ident: &String, value: Constant) {
+        self.insert_var(ident, Variable { constant: true, id: 0, used: false, lambda: None, value: Some(value) });
+    }
+
+    fn insert_var(&mut self, ident: &String, var: Variable) {
         let current_scope = self.scopes.last_mut().unwrap();
         
-        // make sure the variable doesn't exist first (takes up unnecessary memory)
-        if current_scope.contains_key(ident) {
-            eprintln!("Generator Error: Variable '{}' already exists in the current scope.", ident);
-            process::exit(1);
+        // declaring a variable that already exists in the current scope shadows it, the new one gets its own ID
+        // the old value can never be read again after this, so warn if it was never read in the first place
+        if let Some(previous) = current_scope.get(ident) && !previous.used {
+            self.warnings.push(Diagnostic::warning(Stage::Generator, format!("Variable '{}' shadows an earlier '{}' in the same scope whose value is never read.", ident, ident)));
         }
         
-        current_scope.insert(ident.clone(), Variable { 
-            constant,
-            id: self.next_var_id
-        });
-        
-        self.next_var_id += 1;
+        current_scope.insert(ident.clone(), var);
     }
 
-    fn get_var(&mut self, ident: &String) -> &Variable {
-        for scope in self.scopes.iter().rev() {
-            if let Some(var) = scope.get(ident) {
-                return var;
+    // the innermost declaration wins, so variables in inner scopes (or later in the same scope) shadow earlier ones
+    fn get_var(&mut self, ident: &String) -> Result<&mut Variable, Diagnostic> {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(var) = scope.get_mut(ident) {
+                return Ok(var);
             }
         }
         
-        eprintln!("Generator Error: Variable '{}' does not exist in any accessible scope.", ident);
-        process::exit(1);
+        Err(Diagnostic::error(Stage::Generator, format!("Variable '{}' does not exist in any accessible scope.", ident)))
+    }
+
+    fn has_var(&self, ident: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(ident))
+    }
+
+    // globals are loaded from their slot, lambda parameters (and variables in lambda blocks) from the locals of the current call
+    // constants with a known value are pushed instead, so they also work inside lambdas nested in the one they belong to
+    fn generate_load(&mut self, ident: &String) -> Result<(), Diagnostic> {
+        let lambda_depth = self.lambda_depth;
+        let var = self.get_var(ident)?;
+        var.used = true;
+        let (id, lambda) = (var.id as u64, var.lambda);
+
+        if let Some(value) = var.value.clone() {
+            return self.generate_constant(&value);
+        }
+
+        match lambda {
+            Some(depth) if depth != lambda_depth => {
+                return Err(Diagnostic::error(Stage::Generator, format!("'{}' belongs to an outer lambda, lambdas can't use the parameters and variables of the lambda they are in yet.", ident)));
+            }
+            Some(_) => self.bytecode.push(constants::LOAD_LOCAL),
+            None => self.bytecode.push(constants::LOAD),
+        }
+        self.bytecode.extend(self.emit_u64(id));
+
+        Ok(())
     }
 
-    fn generate_operator(&mut self, operator: &str) {
+    // the body is written right where the lambda is, with a jump over it, and the lambda itself is just its address
+    fn generate_lambda(&mut self, lambda: &ast::Lambda) -> Result<(), Diagnostic> {
+        self.bytecode.push(constants::JMP);
+        let jmp_over_pos = self.bytecode.len();
+        self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
+
+        let start = self.bytecode.len();
+        self.lambda_depth += 1;
+        self.local_counts.push(lambda.params.len());
+
+        // CALL_VALUE puts the arguments in locals 0, 1, 2 and so on
+        let params = lambda.params.iter().enumerate().map(|(slot, param)| {
+            (param.name.clone(), Variable { constant: true, id: slot, used: true, lambda: Some(self.lambda_depth), value: None })
+        });
+        self.scopes.push(params.collect());
+
+        match &lambda.body {
+            ast::LambdaBody::Expr(body) => {
+                self.generate_expr(body)?;
+                self.bytecode.push(constants::RET);
+            }
+            // the checker made sure only generators have a return type along with a block
+            ast::LambdaBody::Block(block) if lambda.return_type.is_none() => {
+                self.generate_block_stmt(block)?;
+                self.bytecode.push(constants::RET_VOID);
+            }
+            ast::LambdaBody::Block(block) => {
+                // GEN_START <local count>, the count is only known once the body has been generated
+                self.bytecode.push(constants::GEN_START);
+                let local_count_pos = self.bytecode.len();
+                self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
+
+                self.generate_block_stmt(block)?;
+                self.bytecode.push(constants::GEN_END);
+
+                let local_count = *self.local_counts.last().unwrap();
+                self.patch_jump(local_count_pos, local_count);
+            }
+        }
+
+        self.scopes.pop();
+        self.local_counts.pop();
+        self.lambda_depth -= 1;
+
+        let after_body = self.bytecode.len();
+        self.patch_jump(jmp_over_pos, after_body);
+
+        // PUSH_FN <address> <argument count>
+        self.bytecode.push(constants::PUSH_FN);
+        self.bytecode.extend(self.emit_u64(start as u64));
+        self.bytecode.push(lambda.params.len() as u8);
+
+        Ok(())
+    }
+
+    // calling anything that isn't called by name, the lambda is pushed after its arguments
+    fn generate_value_call(&mut self, callee: &ast::Expr, argc: usize) -> Result<(), Diagnostic> {
+        self.generate_expr(callee)?;
+        self.bytecode.push(constants::CALL_VALUE);
+        self.bytecode.push(argc as u8);
+
+        Ok(())
+    }
+
+    // the same rule the checker uses, a variable with the name of a module hides it
+    fn is_module(&self, object: &ast::Expr) -> bool {
+        matches!(object, ast::Expr::Identifier(identifier) if stdlib::is_module(&identifier.symbol) && !self.has_var(&identifier.symbol))
+    }
+
+    fn generate_operator(&mut self, operator: &str) -> Result<(), Diagnostic> {
         match operator {
             "+" | "+=" => {
                 self.bytecode.push(constants::ADD);
@@ -111,22 +261,154 @@ impl Codegen {
             "<" => {
                 self.bytecode.push(constants::LT);
             }
+            "<=" => {
+                self.bytecode.push(constants::LE);
+            }
+            ">=" => {
+                self.bytecode.push(constants::GE);
+            }
             _ => {
                 // Handle other operators or throw an error if the operator is not supported
-                panic!("Generator Error: Invalid operator: {}", operator);
+                return Err(Diagnostic::error(Stage::Generator, format!("Invalid operator: {}", operator)));
             }
         }
+
+        Ok(())
     }
 
-    fn generate_binary_expr(&mut self, binaryexpr: &ast::BinaryExpr) {
-        self.generate_expr(&binaryexpr.left);
-        self.generate_expr(&binaryexpr.right);
-        self.generate_operator(&binaryexpr.operator);
+    // works out the value of every operation made of literals and constants in the expression, each of them only once
+    // && and || only look at their right hand side when the VM would, so false && 1 / 0 == 1 is just false
+    fn fold(&mut self, expr: &ast::Expr) -> Result<Folded, Diagnostic> {
+        self.nest()?;
+        let folded = self.fold_kind(expr);
+        self.depth -= 1;
+        folded
     }
 
-    fn generate_logical_expr(&mut self, logical_expr: &ast::LogicalExpr) {
+    fn fold_kind(&mut self, expr: &ast::Expr) -> Result<Folded, Diagnostic> {
+        Ok(match expr {
+            ast::Expr::NumericLiteral(literal) => Folded::Constant(Constant::Int(literal.value)),
+            ast::Expr::FloatLiteral(literal) => Folded::Constant(Constant::Float(literal.value)),
+            ast::Expr::BooleanLiteral(literal) => Folded::Constant(Constant::Bool(literal.value)),
+            ast::Expr::StringLiteral(literal) => Folded::Constant(Constant::Str(literal.value.clone())),
+            ast::Expr::Identifier(identifier) => {
+                let Some(var) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(&identifier.symbol)) else {
+                    return Ok(Folded::Runtime);
+                };
+                var.used |= var.value.is_some();
+                var.value.clone().map_or(Folded::Runtime, Folded::Constant)
+            }
+            ast::Expr::Binary(binary_expr) => {
+                let (left, right) = (self.fold(&binary_expr.left)?, self.fold(&binary_expr.right)?);
+                if let (Folded::Constant(l), Folded::Constant(r)) = (&left, &right) && let Some(value) = fold::binary(&binary_expr.operator, l, r)? {
+                    return Ok(Folded::Constant(value));
+                }
+                Folded::Binary(Box::new(left), Box::new(right))
+            }
+            ast::Expr::UnaryExpr(unary_expr) => {
+                let value = self.fold(&unary_expr.value)?;
+                if let Folded::Constant(v) = &value && let Some(value) = fold::unary(&unary_expr.operator, v)? {
+                    return Ok(Folded::Constant(value));
+                }
+                Folded::Unary(Box::new(value))
+            }
+            ast::Expr::LogicalExpr(logical_expr) => {
+                let left = self.fold(&logical_expr.left)?;
+                match (logical_expr.operator.as_str(), &left) {
+                    ("&&", Folded::Constant(Constant::Bool(false))) => return Ok(Folded::Constant(Constant::Bool(false))),
+                    ("||", Folded::Constant(Constant::Bool(true))) => return Ok(Folded::Constant(Constant::Bool(true))),
+                    _ => {}
+                }
+                let right = self.fold(&logical_expr.right)?;
+                match (logical_expr.operator.as_str(), &left, right) {
+                    ("&&" | "||", Folded::Constant(Constant::Bool(_)), Folded::Constant(value)) => Folded::Constant(value),
+                    (_, _, right) => Folded::Logical(Box::new(left), Box::new(right)),
+                }
+            }
+            _ => Folded::Runtime,
+        })
+    }
+
+    // generates an expression with what folding found out about it, so the operations in it aren't folded again
+    fn generate_folded(&mut self, expr: &ast::Expr, folded: &Folded) -> Result<(), Diagnostic> {
+        self.nest()?;
+        let generated = self.generate_folded_kind(expr, folded);
+        self.depth -= 1;
+        generated
+    }
+
+    fn generate_folded_kind(&mut self, expr: &ast::Expr, folded: &Folded) -> Result<(), Diagnostic> {
+        match (expr, folded) {
+            (_, Folded::Constant(value)) => self.generate_constant(value),
+            (ast::Expr::Binary(binary_expr), Folded::Binary(left, right)) => self.generate_binary_expr(binary_expr, left, right),
+            (ast::Expr::UnaryExpr(unary_expr), Folded::Unary(value)) => self.generate_unary_expr(unary_expr, value),
+            (ast::Expr::LogicalExpr(logical_expr), Folded::Logical(left, right)) => self.generate_logical_expr(logical_expr, left, right),
+            _ => self.generate_expr_kind(expr), // already one level deeper for this expression
+        }
+    }
+
+    fn generate_constant(&mut self, value: &Constant) -> Result<(), Diagnostic> {
+        match value {
+            Constant::Int(value) => {
+                self.bytecode.push(constants::PUSH_INT);
+                self.bytecode.extend(self.emit_u64(*value as u64));
+            }
+            Constant::Float(value) => {
+                self.bytecode.push(constants::PUSH_FLOAT);
+                self.bytecode.extend(self.emit_u64(value.to_bits()));
+            }
+            Constant::Bool(value) => {
+                self.bytecode.push(constants::PUSH_BOOL);
+                self.bytecode.push(*value as u8);
+            }
+            Constant::Str(value) => {
+                self.bytecode.push(constants::PUSH_STR);
+                self.bytecode.extend(self.emit_str(value)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_binary_expr(&mut self, binaryexpr: &ast::BinaryExpr, left: &Folded, right: &Folded) -> Result<(), Diagnostic> {
+        self.generate_folded(&binaryexpr.left, left)?;
+        self.generate_folded(&binaryexpr.right, right)?;
+        self.generate_operator(&binaryexpr.operator)?;
+
+        Ok(())
+    }
+
+    fn generate_unary_expr(&mut self, unary_expr: &ast::UnaryExpr, value: &Folded) -> Result<(), Diagnostic> {
+        // push bytecode depending on the operator
+
+        match unary_expr.operator.as_str() {
+            "+" => {
+                // generate the expression normally
+                self.generate_folded(&unary_expr.value, value)?;
+            }
+            "-" => {
+                // generate the expression
+                self.generate_folded(&unary_expr.value, value)?;
+                // add neg opcode to make the result a negative value
+                self.bytecode.push(constants::NEG);
+            }
+            "!" => {
+                // generate the expression
+                self.generate_folded(&unary_expr.value, value)?;
+                // add not opcode
+                self.bytecode.push(constants::NOT)
+            }
+            _ => {
+                return Err(Diagnostic::error(Stage::Generator, format!("Unexpected operator for unary expression.\nAllowed operators are: +, -\nOperator used was: {}", unary_expr.operator)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_logical_expr(&mut self, logical_expr: &ast::LogicalExpr, left: &Folded, right: &Folded) -> Result<(), Diagnostic> {
         // generate the left side, will either push true or false to the stack once evaluated
-        self.generate_expr(&logical_expr.left);
+        self.generate_folded(&logical_expr.left, left)?;
         
         match logical_expr.operator.as_str() {
             "||" => {
@@ -136,7 +418,7 @@ impl Codegen {
                 self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
                 
                 // generate the right side
-                self.generate_expr(&logical_expr.right);
+                self.generate_folded(&logical_expr.right, right)?;
                 // the right side is generated and evaluated, its result stays on the stack.
                 
                 let after_right_pos = self.bytecode.len();
@@ -151,98 +433,207 @@ impl Codegen {
                 // pop the value from above
                 self.bytecode.push(constants::POP);
                 // generate the right side
-                self.generate_expr(&logical_expr.right);
+                self.generate_folded(&logical_expr.right, right)?;
                 // the right side is generated and evaluated, its result stays on the stack.
 
                 let after_right_pos = self.bytecode.len();
                 self.patch_jump(left_pos, after_right_pos);
             }
+            "??" => {
+                // if the left side is not none, skip evaluating the right and keep the left on the stack
+                self.bytecode.push(constants::JMP_IF_NOT_NONE_PEEK);
+                let left_pos = self.bytecode.len();
+                self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
+
+                // pop the none from above and use the right side instead
+                self.bytecode.push(constants::POP);
+                self.generate_folded(&logical_expr.right, right)?;
+
+                let after_right_pos = self.bytecode.len();
+                self.patch_jump(left_pos, after_right_pos);
+            }
             _ => {
-                eprintln!("Generator Error: Invalid logical operator: {}", logical_expr.operator);
-                process::exit(1);
+                return Err(Diagnostic::error(Stage::Generator, format!("Invalid logical operator: {}", logical_expr.operator)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_call_expr(&mut self, call_expr: &ast::CallExpr) -> Result<(), Diagnostic> {
+        for arg in &call_expr.args {
+            self.generate_expr(arg)?;
+        }
+
+        // the checker makes sure the callee is either a builtin, an extern function, a lambda, a module function or a type
+        let identifier = match &*call_expr.callee {
+            ast::Expr::Identifier(identifier) => identifier,
+            ast::Expr::Member(member_expr) if self.is_module(&member_expr.object) => return self.generate_member_expr(member_expr, call_expr.args.len()),
+            callee => return self.generate_value_call(callee, call_expr.args.len()),
+        };
+
+        match identifier.symbol.as_str() {
+            "print" | "println" => {
+                let opcode = if identifier.symbol == "print" { constants::PRINT } else { constants::PRINTLN };
+                self.bytecode.push(opcode);
+                self.bytecode.push(call_expr.args.len() as u8);
+            }
+            "read_line" => self.bytecode.push(constants::READ_LINE),
+            "input" => {
+                // print the prompt, READ_LINE flushes it before waiting for input
+                self.bytecode.push(constants::PRINT);
+                self.bytecode.push(1);
+                self.bytecode.push(constants::READ_LINE);
             }
+            "parse_int" => self.bytecode.push(constants::PARSE_INT),
+            "args" => self.bytecode.push(constants::ARGS),
+            "env" => self.bytecode.push(constants::ENV),
+            "exit" => self.bytecode.push(constants::EXIT),
+            "send" => self.bytecode.push(constants::SEND),
+            "receive" => self.bytecode.push(constants::RECEIVE),
+            "float" => self.bytecode.push(constants::TO_FLOAT),
+            "str" => self.bytecode.push(constants::TO_STR),
+            name if self.has_var(name) => self.generate_value_call(&call_expr.callee, call_expr.args.len())?,
+            name if self.externs.contains_key(name) => {
+                // CALL_NATIVE <name> <argument count>
+                self.bytecode.push(constants::CALL_NATIVE);
+                self.bytecode.extend(self.emit_str(name)?);
+                self.bytecode.push(call_expr.args.len() as u8);
+
+                // natives without a return type still push none, nothing will ever read it
+                if !self.externs[name] {
+                    self.bytecode.push(constants::POP);
+                }
+            }
+            _ => { /* converting between a newtype and what it wraps, they are the same value at runtime */ }
         }
+
+        Ok(())
     }
 
-    fn generate_assignment_expr(&mut self, assignmentexpr: &ast::AssignmentExpr) {
+    // math.sqrt(x) and math.PI both call the native function the VM registered for them, constants just don't take any arguments
+    fn generate_member_expr(&mut self, member_expr: &ast::MemberExpr, argc: usize) -> Result<(), Diagnostic> {
+        let ast::Expr::Identifier(module) = &*member_expr.object else {
+            return Err(Diagnostic::error(Stage::Generator, format!("Only modules have members, '{}' can't be accessed here.", member_expr.property)));
+        };
+
+        let Some(member) = stdlib::member(&module.symbol, &member_expr.property) else {
+            return Err(Diagnostic::error(Stage::Generator, format!("Module '{}' has no member named '{}'.", module.symbol, member_expr.property)));
+        };
+
+        // CALL_NATIVE <name> <argument count>
+        self.bytecode.push(constants::CALL_NATIVE);
+        self.bytecode.extend(self.emit_str(&stdlib::native_name(&module.symbol, &member_expr.property))?);
+        self.bytecode.push(argc as u8);
+
+        if let Member::Function { ret: Type::Void, .. } = member {
+            self.bytecode.push(constants::POP);
+        }
+
+        Ok(())
+    }
+
+    fn generate_assignment_expr(&mut self, assignmentexpr: &ast::AssignmentExpr) -> Result<(), Diagnostic> {
         // make sure the assignee is an identifier (only one currently supported)
 
         let assignee = &*assignmentexpr.assignee;
         let ident: String = match assignee {
             ast::Expr::Identifier(identifier) => { (*identifier.symbol).to_string() },
             _ => {
-                eprintln!("Generator Error: The left hand side of the assignment expression was not an identifier.");
-                process::exit(1);
+                return Err(Diagnostic::error(Stage::Generator, "The left hand side of the assignment expression was not an identifier."));
             }
         };
 
         // make sure the variable that the assignee is referring to exists
-        let var = self.get_var(&ident);
-        let var_id = var.id as u64;
+        let lambda_depth = self.lambda_depth;
+        let var = self.get_var(&ident)?;
+        let (var_id, lambda) = (var.id, var.lambda);
+        if lambda.is_some_and(|depth| depth != lambda_depth) {
+            return Err(Diagnostic::error(Stage::Generator, format!("'{}' belongs to an outer lambda, lambdas can't use the parameters and variables of the lambda they are in yet.", ident)));
+        }
 
         // make sure that the variable is not constant
 
         if var.constant {
-            eprintln!("Generator Error: Attempted to assign to constant '{}', which is immutable.", ident);
-            process::exit(1);
+            return Err(Diagnostic::error(Stage::Generator, format!("Attempted to assign to constant '{}', which is immutable.", ident)));
         }
 
         // if the assignment operator is not =, also push the value of the assignee before the assignment
         if assignmentexpr.operator != "=" {
-            self.generate_expr(assignee);
+            self.generate_expr(assignee)?;
         }
 
-        self.generate_expr(&assignmentexpr.value);
-        self.generate_operator(&assignmentexpr.operator);
-        self.bytecode.push(constants::STORE);
-        self.bytecode.extend(self.emit_u64(var_id));
+        self.generate_expr(&assignmentexpr.value)?;
+        self.generate_operator(&assignmentexpr.operator)?;
+        self.emit_store(var_id, lambda);
+
+        Ok(())
     }
 
-    fn generate_expr(&mut self, expr: &ast::Expr) {
+    fn generate_expr(&mut self, expr: &ast::Expr) -> Result<(), Diagnostic> {
+        self.nest()?;
+        let generated = self.generate_expr_kind(expr);
+        self.depth -= 1;
+        generated
+    }
+
+    fn generate_expr_kind(&mut self, expr: &ast::Expr) -> Result<(), Diagnostic> {
         match expr {
-            ast::Expr::Binary(binary_expr) => self.generate_binary_expr(binary_expr),
-            ast::Expr::Identifier(identifier) => {
-                // check if a variable exists and get its id if it does
-                let val = self.get_var(&identifier.symbol);
-                let val_u64: u64 = val.id as u64;
-                
-                self.bytecode.push(constants::LOAD);
-                self.bytecode.extend(self.emit_u64(val_u64));
+            // operations on literals and constants are worked out right here, the VM only gets their value
+            ast::Expr::Binary(_) | ast::Expr::UnaryExpr(_) | ast::Expr::LogicalExpr(_) => {
+                let folded = self.fold_kind(expr)?;
+                self.generate_folded_kind(expr, &folded)?;
+            }
+            ast::Expr::Identifier(identifier) => self.generate_load(&identifier.symbol)?,
+            ast::Expr::ListLiteral(list) => {
+                for item in &list.items {
+                    self.generate_expr(item)?;
+                }
+                self.bytecode.push(constants::MAKE_LIST);
+                self.bytecode.extend(self.emit_u64(list.items.len() as u64));
+            }
+            ast::Expr::MapLiteral(map) => {
+                for (key, value) in &map.entries {
+                    self.generate_expr(key)?;
+                    self.generate_expr(value)?;
+                }
+                self.bytecode.push(constants::MAKE_MAP);
+                self.bytecode.extend(self.emit_u64(map.entries.len() as u64));
             }
+            ast::Expr::TupleLiteral(tuple) => {
+                for item in &tuple.items {
+                    self.generate_expr(item)?;
+                }
+                self.bytecode.push(constants::MAKE_TUPLE);
+                self.bytecode.push(tuple.items.len() as u8);
+            }
+            ast::Expr::Lambda(lambda) => self.generate_lambda(lambda)?,
+            ast::Expr::ChannelExpr(_) => self.bytecode.push(constants::MAKE_CHANNEL),
             ast::Expr::NumericLiteral(literal) => {
                 let val_u64: u64 = literal.value as u64;
 
                 self.bytecode.push(constants::PUSH_INT);
                 self.bytecode.extend(self.emit_u64(val_u64));
             }
-            ast::Expr::UnaryExpr(unary_expr) => {
-                // push bytecode depending on the operator
-
-                match unary_expr.operator.as_str() {
-                    "+" => {
-                        // generate the expression normally
-                        self.generate_expr(&unary_expr.value);
-                    }
-                    "-" => {
-                        // generate the expression
-                        self.generate_expr(&unary_expr.value);
-                        // add neg opcode to make the result a negative value
-                        self.bytecode.push(constants::NEG);
-                    }
-                    "!" => {
-                        // generate the expression
-                        self.generate_expr(&unary_expr.value);
-                        // add not opcode
-                        self.bytecode.push(constants::NOT)
-                    }
-                    _ => {
-                        eprintln!("Generator Error: Unexpected operator for unary expression.\nAllowed operators are: +, -\nOperator used was: {}", unary_expr.operator);
-                        process::exit(1);
-                    }
-                }
+            ast::Expr::FloatLiteral(literal) => {
+                self.bytecode.push(constants::PUSH_FLOAT);
+                self.bytecode.extend(self.emit_u64(literal.value.to_bits()));
             }
-            ast::Expr::LogicalExpr(logical_expr) => self.generate_logical_expr(logical_expr),
-            ast::Expr::AssignmentExpr(assignment_expr) => self.generate_assignment_expr(assignment_expr),
+            ast::Expr::Member(member_expr) if self.is_module(&member_expr.object) => self.generate_member_expr(member_expr, 0)?,
+            ast::Expr::Member(member_expr) => {
+                // pair.0, the checker made sure the index is in range
+                let index = member_expr.property.parse::<u8>().map_err(|_| Diagnostic::error(Stage::Generator, format!("'{}' is not a valid tuple item.", member_expr.property)))?;
+                self.generate_expr(&member_expr.object)?;
+                self.bytecode.push(constants::TUPLE_GET);
+                self.bytecode.push(index);
+            }
+            ast::Expr::Call(call_expr) => self.generate_call_expr(call_expr)?,
+            ast::Expr::AssignmentExpr(assignment_expr) => self.generate_assignment_expr(assignment_expr)?,
+            ast::Expr::StringLiteral(literal) => {
+                self.bytecode.push(constants::PUSH_STR);
+                self.bytecode.extend(self.emit_str(&literal.value)?);
+            }
+            ast::Expr::NoneLiteral(_) => self.bytecode.push(constants::PUSH_NONE),
             ast::Expr::BooleanLiteral(literal) => {
                 self.bytecode.push(constants::PUSH_BOOL);
                 if literal.value {
@@ -252,6 +643,8 @@ impl Codegen {
                 }
             }
         }
+
+        Ok(())
     }
 
     // this function needs to exist because the if statement does not know where to jump because that area is not yet generated, once it is, we can patch the placeholder with the real
@@ -260,9 +653,9 @@ impl Codegen {
         self.bytecode[pos..(pos + 8)].copy_from_slice(&target_bytes);
     }
 
-    fn generate_if_stmt(&mut self, if_stmt: &ast::IfStatement) {
+    fn generate_if_stmt(&mut self, if_stmt: &ast::IfStatement) -> Result<(), Diagnostic> {
         // generate condition
-        self.generate_expr(&if_stmt.test);
+        self.generate_expr(&if_stmt.test)?;
 
         // emit the jmpiffalse opcode
         self.bytecode.push(constants::JMP_IF_FALSE);
@@ -270,7 +663,7 @@ impl Codegen {
         self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
 
         // generate code for if statement body
-        self.generate_stmt(&if_stmt.body);
+        self.generate_stmt(&if_stmt.body)?;
 
         if let Some(alternate) = &if_stmt.alternate {
             self.bytecode.push(constants::JMP);
@@ -282,7 +675,7 @@ impl Codegen {
             self.patch_jump(jmp_if_false_pos, alternate_start);
 
             // generate the alternate's stmt
-            self.generate_stmt(alternate);
+            self.generate_stmt(alternate)?;
 
             // patch jmp from earlier to after the else block
             let after_else = self.bytecode.len();
@@ -292,58 +685,310 @@ impl Codegen {
             let after_body = self.bytecode.len();
             self.patch_jump(jmp_if_false_pos, after_body);
         }
+
+        Ok(())
+    }
+
+    fn generate_assert_stmt(&mut self, assert_stmt: &ast::AssertStatement) -> Result<(), Diagnostic> {
+        // the message goes below the test on the stack, ASSERT pops the test first
+        if let Some(message) = &assert_stmt.message {
+            self.generate_expr(message)?;
+        }
+        self.generate_expr(&assert_stmt.test)?;
+
+        // ASSERT <line> <has message> <source>
+        self.bytecode.push(constants::ASSERT);
+        self.bytecode.extend(self.emit_u64(assert_stmt.line as u64));
+        self.bytecode.push(assert_stmt.message.is_some() as u8);
+
+        // strings can only hold 255 bytes, cut long tests short (on a char boundary) since they're only for showing
+        let mut source = assert_stmt.source.clone();
+        if source.len() > u8::MAX as usize {
+            let mut end = u8::MAX as usize - 3;
+            while !source.is_char_boundary(end) {
+                end -= 1;
+            }
+            source.truncate(end);
+            source.push_str("...");
+        }
+        self.bytecode.extend(self.emit_str(&source)?);
+
+        Ok(())
     }
 
-    fn generate_block_stmt(&mut self, block_stmt: &ast::BlockStatement) {
+    fn generate_for_stmt(&mut self, for_stmt: &ast::ForStatement) -> Result<(), Diagnostic> {
+        // <iterable> GET_ITER STORE <iterator>
+        // loop: LOAD <iterator> ITER_NEXT <end> STORE <variable> <body> JMP <loop>
+        // end:
+        // the iterator lives in a variable of its own, so values the body leaves on the stack can't get in the way
+        self.generate_expr(&for_stmt.iterable)?;
+        self.bytecode.push(constants::GET_ITER);
+        let (iterator, lambda) = self.next_slot();
+        self.emit_store(iterator, lambda);
+
+        let loop_start = self.bytecode.len();
+        self.bytecode.push(if lambda.is_some() { constants::LOAD_LOCAL } else { constants::LOAD });
+        self.bytecode.extend(self.emit_u64(iterator as u64));
+        self.bytecode.push(constants::ITER_NEXT);
+        let iter_next_pos = self.bytecode.len();
+        self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
+
+        // the loop variable gets a scope of its own, it can't be assigned to
+        self.scopes.push(HashMap::new());
+        self.set_var(&for_stmt.variable, true);
+        let var = self.get_var(&for_stmt.variable)?;
+        let (id, lambda) = (var.id, var.lambda);
+        self.emit_store(id, lambda);
+
+        self.generate_stmt(&for_stmt.body)?;
+        self.scopes.pop();
+
+        self.bytecode.push(constants::JMP);
+        self.bytecode.extend(self.emit_u64(loop_start as u64));
+
+        let loop_end = self.bytecode.len();
+        self.patch_jump(iter_next_pos, loop_end);
+
+        Ok(())
+    }
+
+    fn generate_spawn_stmt(&mut self, spawn_stmt: &ast::SpawnStatement) -> Result<(), Diagnostic> {
+        // <arguments> <function> SPAWN <argument count> <end> JMP <after>
+        // end: TASK_END
+        // after:
+        // the task returns to the TASK_END once its function is done, the code that spawned it jumps over it
+        for arg in &spawn_stmt.args {
+            self.generate_expr(arg)?;
+        }
+        self.generate_expr(&spawn_stmt.function)?;
+
+        self.bytecode.push(constants::SPAWN);
+        self.bytecode.push(spawn_stmt.args.len() as u8);
+        let task_end_pos = self.bytecode.len();
+        self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
+
+        self.bytecode.push(constants::JMP);
+        let jmp_over_pos = self.bytecode.len();
+        self.bytecode.extend(self.emit_u64(0)); // placeholder bytes
+
+        let task_end = self.bytecode.len();
+        self.patch_jump(task_end_pos, task_end);
+        self.bytecode.push(constants::TASK_END);
+
+        let after = self.bytecode.len();
+        self.patch_jump(jmp_over_pos, after);
+
+        Ok(())
+    }
+
+    // marks every variable the expression names as used for the shadowing lint, without generating anything
+    // a name that turns out to be a lambda parameter marks the outer variable too, which at worst hides a warning
+    fn mark_used(&mut self, expr: &ast::Expr) {
+        // past the nesting limit generating the expression gives the error, this only has to stop going deeper
+        if self.nest().is_err() {
+            return;
+        }
+        match expr {
+            ast::Expr::Identifier(identifier) => {
+                if let Ok(var) = self.get_var(&identifier.symbol) {
+                    var.used = true;
+                }
+            }
+            ast::Expr::Binary(ast::BinaryExpr { left, right, .. }) | ast::Expr::LogicalExpr(ast::LogicalExpr { left, right, .. }) => {
+                self.mark_used(left);
+                self.mark_used(right);
+            }
+            ast::Expr::AssignmentExpr(assignment) => {
+                self.mark_used(&assignment.assignee);
+                self.mark_used(&assignment.value);
+            }
+            ast::Expr::UnaryExpr(unary_expr) => self.mark_used(&unary_expr.value),
+            ast::Expr::Call(call_expr) => {
+                self.mark_used(&call_expr.callee);
+                call_expr.args.iter().for_each(|arg| self.mark_used(arg));
+            }
+            ast::Expr::Member(member_expr) => self.mark_used(&member_expr.object),
+            ast::Expr::ListLiteral(ast::ListLiteral { items, .. }) | ast::Expr::TupleLiteral(ast::TupleLiteral { items, .. }) => {
+                items.iter().for_each(|item| self.mark_used(item));
+            }
+            ast::Expr::MapLiteral(map) => {
+                for (key, value) in &map.entries {
+                    self.mark_used(key);
+                    self.mark_used(value);
+                }
+            }
+            ast::Expr::Lambda(lambda) => match &lambda.body {
+                ast::LambdaBody::Expr(body) => self.mark_used(body),
+                ast::LambdaBody::Block(block) => block.body.iter().for_each(|stmt| self.mark_used_stmt(stmt)),
+            },
+            ast::Expr::NumericLiteral(_) | ast::Expr::FloatLiteral(_) | ast::Expr::BooleanLiteral(_) | ast::Expr::StringLiteral(_) | ast::Expr::NoneLiteral(_) | ast::Expr::ChannelExpr(_) => {}
+        }
+        self.depth -= 1;
+    }
+
+    fn mark_used_stmt(&mut self, stmt: &ast::Stmt) {
+        if self.nest().is_err() {
+            return;
+        }
+        match stmt {
+            ast::Stmt::Program(program) => program.body.iter().for_each(|stmt| self.mark_used_stmt(stmt)),
+            ast::Stmt::BlockStatement(block) => block.body.iter().for_each(|stmt| self.mark_used_stmt(stmt)),
+            ast::Stmt::VariableDeclaration(vardecl) => self.mark_used(&vardecl.value),
+            ast::Stmt::IfStatement(if_stmt) => {
+                self.mark_used(&if_stmt.test);
+                self.mark_used_stmt(&if_stmt.body);
+                if let Some(alternate) = &if_stmt.alternate {
+                    self.mark_used_stmt(alternate);
+                }
+            }
+            ast::Stmt::ForStatement(for_stmt) => {
+                self.mark_used(&for_stmt.iterable);
+                self.mark_used_stmt(&for_stmt.body);
+            }
+            ast::Stmt::YieldStatement(ast::YieldStatement { value, .. }) | ast::Stmt::DeferStatement(ast::DeferStatement { value, .. }) | ast::Stmt::Expr(value) => self.mark_used(value),
+            ast::Stmt::SpawnStatement(spawn_stmt) => {
+                self.mark_used(&spawn_stmt.function);
+                spawn_stmt.args.iter().for_each(|arg| self.mark_used(arg));
+            }
+            ast::Stmt::AssertStatement(assert_stmt) => {
+                self.mark_used(&assert_stmt.test);
+                if let Some(message) = &assert_stmt.message {
+                    self.mark_used(message);
+                }
+            }
+            ast::Stmt::TypeAlias(_) | ast::Stmt::NewtypeDeclaration(_) | ast::Stmt::ExternFunctionDeclaration(_) => {}
+        }
+        self.depth -= 1;
+    }
+
+    fn generate_defer_stmt(&mut self, defer_stmt: &ast::DeferStatement) -> Result<(), Diagnostic> {
+        // the expression is only generated when the block ends, but a later shadowing declaration shouldn't warn about the variables it reads
+        self.mark_used(&defer_stmt.value);
+
+        // the checker made sure defer is only used directly inside a block
+        let scope = self.scopes.last().unwrap().clone();
+        self.deferred.last_mut().unwrap().push((defer_stmt.value.clone(), scope));
+
+        Ok(())
+    }
+
+    fn generate_block_stmt(&mut self, block_stmt: &ast::BlockStatement) -> Result<(), Diagnostic> {
         self.scopes.push(HashMap::new()); // make a new scope
+        self.deferred.push(Vec::new());
         
         // generate code for each statement in the block
         for stmt in &block_stmt.body {
-            self.generate_stmt(stmt);
+            self.generate_stmt(stmt)?;
+        }
+
+        // the deferred expressions run last to first, each sees the variables that were in scope where it was deferred
+        // a later declaration can't change what a name in it refers to (let x = 1 defer println(x) let x = 2 prints 1)
+        // they are generated where the block ends, so leaving it through exit() or a runtime error skips them
+        for (value, scope) in self.deferred.pop().unwrap().into_iter().rev() {
+            *self.scopes.last_mut().unwrap() = scope;
+            self.generate_expr(&value)?;
         }
         
         self.scopes.pop(); // exit the scope
+
+        Ok(())
     }
 
-    fn generate_stmt(&mut self, stmt: &ast::Stmt) {
+    fn generate_stmt(&mut self, stmt: &ast::Stmt) -> Result<(), Diagnostic> {
+        self.nest()?;
+        let generated = self.generate_stmt_kind(stmt);
+        self.depth -= 1;
+        generated
+    }
+
+    fn generate_stmt_kind(&mut self, stmt: &ast::Stmt) -> Result<(), Diagnostic> {
         match stmt {
-            ast::Stmt::VariableDeclaration(vardecl) => self.generate_vardecl_stmt(vardecl),
-            ast::Stmt::IfStatement(if_stmt) => self.generate_if_stmt(if_stmt),
-            ast::Stmt::BlockStatement(block_stmt) => self.generate_block_stmt(block_stmt),
-            ast::Stmt::Expr(expr) => self.generate_expr(expr),
+            ast::Stmt::VariableDeclaration(vardecl) => self.generate_vardecl_stmt(vardecl)?,
+            ast::Stmt::TypeAlias(_) | ast::Stmt::NewtypeDeclaration(_) => { /* types only exist at compile time */ }
+            ast::Stmt::ExternFunctionDeclaration(extern_fn) => {
+                self.externs.insert(extern_fn.identifier.clone(), extern_fn.return_type != Type::Void);
+            }
+            ast::Stmt::IfStatement(if_stmt) => self.generate_if_stmt(if_stmt)?,
+            ast::Stmt::ForStatement(for_stmt) => self.generate_for_stmt(for_stmt)?,
+            ast::Stmt::YieldStatement(yield_stmt) => {
+                self.generate_expr(&yield_stmt.value)?;
+                self.bytecode.push(constants::YIELD);
+            }
+            ast::Stmt::SpawnStatement(spawn_stmt) => self.generate_spawn_stmt(spawn_stmt)?,
+            ast::Stmt::DeferStatement(defer_stmt) => self.generate_defer_stmt(defer_stmt)?,
+            ast::Stmt::AssertStatement(assert_stmt) => self.generate_assert_stmt(assert_stmt)?,
+            ast::Stmt::BlockStatement(block_stmt) => self.generate_block_stmt(block_stmt)?,
+            ast::Stmt::Expr(expr) => self.generate_expr(expr)?,
             ast::Stmt::Program(_) => {
-                eprintln!("Generator Error: There is a program within the program, this is not allowed!");
-                process::exit(1);
+                return Err(Diagnostic::error(Stage::Generator, "There is a program within the program, this is not allowed!"));
             }
         }
+
+        Ok(())
     }
 
-    fn generate_vardecl_stmt(&mut self, vardecl: &ast::VariableDeclaration) {
+    fn generate_vardecl_stmt(&mut self, vardecl: &ast::VariableDeclaration) -> Result<(), Diagnostic> {
         // PUSH_INT/PUSH_STR <whatever the variable value is>
         // STORE <next available ID>
 
+        // a constant with a value known at compile time doesn't need a slot, its uses are replaced with the value
+        let folded = self.fold(&vardecl.value)?;
+        if let (true, Folded::Constant(value)) = (vardecl.constant, &folded) {
+            self.set_const(&vardecl.identifier, value.clone());
+            return Ok(());
+        }
+
         // generate the variable's value
-        self.generate_expr(&vardecl.value);
-        // set the variable in the bytecode - STORE <next available ID>
-        self.bytecode.push(constants::STORE);
-        self.bytecode.extend(self.emit_u64(self.next_var_id as u64));
-        // set the variable in the generator so the ID isn't repeated
+        self.generate_folded(&vardecl.value, &folded)?;
+        // set the variable in the generator so the ID isn't repeated, then store the value in it
         self.set_var(&vardecl.identifier, vardecl.constant);
+        let var = self.get_var(&vardecl.identifier)?;
+        let (id, lambda) = (var.id, var.lambda);
+        self.emit_store(id, lambda);
+
+        Ok(())
+    }
+
+    /// Returns the warnings found so far, in the order they were found.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Returns the slot of every variable declared at the top level of the program, pass them to `VM::global` to read the variables after running it.
+    ///
+    /// Shadowed variables only show up with their latest slot, and constants whose value was worked out at compile time are left out
+    /// since they don't have one, `Codegen::constants` has their values.
+    pub fn globals(&self) -> HashMap<String, usize> {
+        self.scopes[0].iter().filter(|(_, var)| var.value.is_none()).map(|(name, var)| (name.clone(), var.id)).collect()
+    }
+
+    /// Returns the value of every constant declared at the top level of the program whose value was worked out at compile time.
+    pub fn constants(&self) -> HashMap<String, Constant> {
+        self.scopes[0].iter().filter_map(|(name, var)| Some((name.clone(), var.value.clone()?))).collect()
     }
 
     /// Generate a bytecode array (that can be written to bytecode files and interpreted) based off the parser's produced AST.
+    /// Stops at the first error, warnings are kept in `Codegen::warnings`.
     /// 
     /// `ast`: A vector of statements
-    pub fn generate(&mut self, ast: Vec<ast::Stmt>) -> &Vec<u8> {
+    pub fn generate(&mut self, ast: Vec<ast::Stmt>) -> Result<&Vec<u8>, Diagnostic> {
         // push the magic number
         self.bytecode.extend(constants::MAGIC_NUMBER_U8);
 
         // iterate through statements and expressions and turn them into operations
         for stmt in ast {
-            self.generate_stmt(&stmt);
+            self.generate_stmt(&stmt)?;
+        }
+
+        // the checker made sure a top level main is a fn(): int, its result is the exit code
+        if self.has_var("main") {
+            self.generate_load(&"main".to_string())?;
+            self.bytecode.push(constants::CALL_VALUE);
+            self.bytecode.push(0);
+            self.bytecode.push(constants::EXIT);
         }
 
         self.bytecode.push(constants::HALT);
-        &self.bytecode
+        Ok(&self.bytecode)
     }
 } 
\ No newline at end of file
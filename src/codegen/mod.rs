@@ -1 +1,2 @@
-pub mod generator;
\ No newline at end of file
+pub mod generator;
+pub mod fold;
\ No newline at end of file
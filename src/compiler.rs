@@ -0,0 +1,52 @@
+// the whole pipeline from source code to bytecode in one call, for programs that embed the compiler
+
+use crate::codegen::generator::Codegen;
+use crate::errors::{Diagnostic, Diagnostics};
+use crate::lexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+use crate::typechecker::checker::Checker;
+
+/// Options for `compile`.
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Fail when there are any warnings, so they end up in the returned diagnostics.
+    pub deny_warnings: bool,
+}
+
+/// Compile source code into bytecode that `VM::new` can run, going through the lexer, parser, checker and generator.
+///
+/// Never panics or exits the process, everything that goes wrong is returned. Code nested deeper than `parser::ast::MAX_DEPTH`
+/// is an error too, so a release build doesn't run out of stack even on a thread with the default 2 MiB. Compiling stops at the first error,
+/// the diagnostics hold it along with the warnings found before it. Warnings are dropped when compiling succeeds, unless `deny_warnings` is set.
+///
+/// ```ignore
+/// let bytecode = cobalt_lang::compile("let x = 5", &CompileOptions::default())?;
+/// ```
+pub fn compile(source: &str, options: &CompileOptions) -> Result<Vec<u8>, Diagnostics> {
+    let tokens = Lexer::new(source.chars().collect()).lex().map_err(|error| Diagnostics(vec![error]))?;
+    let mut ast = Parser::new(tokens).produce_ast().map_err(|error| Diagnostics(vec![error]))?;
+
+    let mut checker = Checker::new();
+    let checked = checker.check(&mut ast.body);
+    let mut diagnostics = checker.warnings().to_vec();
+    fail_on_error(checked, &mut diagnostics)?;
+
+    let mut codegen = Codegen::new();
+    let generated = codegen.generate(ast.body).cloned();
+    diagnostics.extend_from_slice(codegen.warnings());
+    let bytecode = fail_on_error(generated, &mut diagnostics)?;
+
+    if options.deny_warnings && !diagnostics.is_empty() {
+        return Err(Diagnostics(diagnostics));
+    }
+
+    Ok(bytecode)
+}
+
+// adds the error of a failed stage after the warnings that came before it
+fn fail_on_error<T>(result: Result<T, Diagnostic>, diagnostics: &mut Vec<Diagnostic>) -> Result<T, Diagnostics> {
+    result.map_err(|error| {
+        diagnostics.push(error);
+        Diagnostics(std::mem::take(diagnostics))
+    })
+}
@@ -1,15 +1,174 @@
 // This file defines default formatting for error messages in the lexer, parser, generator, and VM.
 
 use colored::*;
-use std::process;
+use std::fmt;
 
 // COMMON VM ERRORS AS CONSTANTS
 
 pub const VMERR_STACK_UNDERFLOW: &str = "Stack underflow!";
 pub const VMERR_STACK_OVERFLOW: &str = "Stack overflow!";
+pub const VMERR_DEADLOCK: &str = "Deadlock! Every task is waiting to receive from a channel that nothing can send to anymore.";
 
-pub fn vm_err(msg: &str, ip: usize) -> ! {
-    eprintln!("{} {}\nIP: {}", "VM Error:".bold().red(), msg, ip);
-    process::exit(1)
+// EXIT CODES
+
+// what cobaltc and cobalt exit with when a program fails, programs can't exit with these themselves so the failures can be told apart
+pub const EXIT_COMPILE_ERROR: i32 = 100;
+pub const EXIT_RUNTIME_ERROR: i32 = 101;
+pub const EXIT_ASSERTION_FAILED: i32 = 102;
+
+/// Why the VM stopped running a program early, returned by `VM::interpret`.
+///
+/// Printing it with `{}` gives the same message `cobalt run` shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmError {
+    /// Something went wrong while running, like dividing by zero or invalid bytecode. `ip` is where it happened.
+    Runtime { message: String, ip: usize },
+    /// An `assert` statement was false. `source` is the asserted expression as it was written.
+    AssertionFailed { message: Option<String>, source: String, line: u64 },
+    /// The program tried to use a path or environment variable the host didn't allow, `path` is the path (or the variable's name) as the program gave it.
+    PermissionDenied { access: Access, path: String, ip: usize },
+    /// The program called `exit(code)`, or its `main` returned `code`. Not a failure, the program asked to stop.
+    Exit { code: i32 },
+}
+
+impl VmError {
+    /// The status `cobalt run` exits with for this error, the program's own code for `Exit`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VmError::Runtime { .. } | VmError::PermissionDenied { .. } => EXIT_RUNTIME_ERROR,
+            VmError::AssertionFailed { .. } => EXIT_ASSERTION_FAILED,
+            VmError::Exit { code } => *code,
+        }
+    }
+}
+
+/// What a program tried to do that needs permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Env, // reading an environment variable
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::Runtime { message, ip } => write!(f, "{} {}\nIP: {}", "VM Error:".bold().red(), message, ip),
+            VmError::AssertionFailed { message, source, line } => {
+                match message {
+                    Some(message) => writeln!(f, "{} {}", "Assertion Failed:".bold().red(), message)?,
+                    None => writeln!(f, "{}", "Assertion Failed!".bold().red())?,
+                }
+                write!(f, "  assert {}\n  at line {}", source, line)
+            }
+            VmError::PermissionDenied { access, path, ip } => {
+                write!(f, "{} ", "Permission Denied:".bold().red())?;
+                match access {
+                    Access::Read => write!(f, "Reading '{}' is not allowed, it has to be inside a directory given to --allow-read.", path)?,
+                    Access::Write => write!(f, "Writing '{}' is not allowed, it has to be inside a directory given to --allow-write.", path)?,
+                    Access::Env => write!(f, "Reading the environment variable '{}' is not allowed without --allow-env.", path)?,
+                }
+                write!(f, "\nIP: {}", ip)
+            }
+            VmError::Exit { code } => write!(f, "The program exited with code {}.", code),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+/// The part of the compiler a diagnostic came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Lexer,
+    Parser,
+    Checker,
+    Generator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// An error or warning found while compiling, with the position in the source when it's known.
+///
+/// Printing it with `{}` gives the same message `cobaltc build` shows, like "Parser Error: ... (line 1, column 5)".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub severity: Severity,
+    pub message: String,
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn error(stage: Stage, message: impl Into<String>) -> Self {
+        Self { stage, severity: Severity::Error, message: message.into(), line: None, col: None }
+    }
+
+    pub fn warning(stage: Stage, message: impl Into<String>) -> Self {
+        Self { stage, severity: Severity::Warning, message: message.into(), line: None, col: None }
+    }
+
+    /// Sets where in the source the diagnostic points to.
+    pub fn at(mut self, line: usize, col: usize) -> Self {
+        self.line = Some(line);
+        self.col = Some(col);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            Stage::Lexer => "Lexer",
+            Stage::Parser => "Parser",
+            Stage::Checker => "Checker",
+            Stage::Generator => "Generator",
+        };
+        let severity = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+
+        write!(f, "{} {}: {}", stage, severity, self.message)?;
+        match (self.line, self.col) {
+            (Some(line), Some(col)) => write!(f, " (line {}, column {})", line, col),
+            (Some(line), None) => write!(f, " (line {})", line),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Everything `compile` found before it stopped, warnings included, in the order they were found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|diagnostic| diagnostic.severity == Severity::Warning)
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
 }
 
+impl std::error::Error for Diagnostics {}
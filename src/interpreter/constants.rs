@@ -6,8 +6,14 @@ pub const MAGIC_NUMBER_U8: [u8; 4] = MAGIC_NUMBER.to_le_bytes();
 // OPCODES - STACK MANIPULATION
 pub const PUSH_INT: u8 = 0x01; // PUSH_INT 4
 pub const PUSH_STR: u8 = 0x02; // PUSH_STR "hello world!"
+pub const PUSH_FLOAT: u8 = 0x22; // PUSH_FLOAT 1.5, the operand is the 8 bytes of the float (IEEE 754, little-endian)
+pub const PUSH_NONE: u8 = 0x1d; // PUSH_NONE, pushes the value of an optional that has nothing in it
 pub const PUSH_BOOL: u8 = 0x18; // PUSH_BOOL <0|1>, 0 for false and 1 for true, although any number other than 0 will evaluate to true
 pub const POP: u8 = 0x03; // POP
+pub const MAKE_LIST: u8 = 0x25; // MAKE_LIST <count>, pops that many values and pushes them as a list, in the order they were pushed
+pub const MAKE_MAP: u8 = 0x26; // MAKE_MAP <count>, pops that many key and value pairs (key pushed first) and pushes them as a map
+pub const MAKE_TUPLE: u8 = 0x27; // MAKE_TUPLE <count byte>, pops that many values and pushes them as a tuple
+pub const TUPLE_GET: u8 = 0x28; // TUPLE_GET <index byte>, pops a tuple and pushes the item at the index
 
 // ARITHMETIC
 pub const ADD: u8 = 0x04; // PUSH_INT 5, PUSH_INT 3, ADD, this pops 5 and 3 and pushes 8 to the stack
@@ -16,6 +22,8 @@ pub const MUL: u8 = 0x06; // MUL
 pub const DIV: u8 = 0x07; // DIV
 pub const MOD: u8 = 0x15; // MOD
 pub const NEG: u8 = 0x17; // NEG (pops the stack and pushes back that value as a negative value, only works on numbers)
+pub const TO_FLOAT: u8 = 0x23; // TO_FLOAT (pops an int and pushes it back as a float, floats are pushed back unchanged)
+pub const TO_STR: u8 = 0x24; // TO_STR (pops any value and pushes it back as the string print would write for it)
 pub const NOT: u8 = 0x19; // NOT (converts a boolean to the opposite of what it currently is, false -> true, true -> false)
 
 // COMPARISONS
@@ -23,6 +31,8 @@ pub const EQ: u8 = 0x08; // PUSH INT 5, PUSH_INT 5, EQ
 pub const NEQ: u8 = 0x09; // PUSH INT 5, PUSH_INT 3, NEQ
 pub const LT: u8 = 0x0a; // PUSH_INT 3, PUSH_INT 5, LT
 pub const GT: u8 = 0x0b; // PUSH_INT 5, PUSH_INT 3, GT
+pub const LE: u8 = 0x3b; // PUSH_INT 3, PUSH_INT 5, LE
+pub const GE: u8 = 0x3c; // PUSH_INT 5, PUSH_INT 3, GE
 
 // CONTROL FLOW
 pub const JMP: u8 = 0x0c; // JMP <address>, The program starts at address 0, each new byte is a new address. the IP variable also refers to an address, for reference.
@@ -30,17 +40,55 @@ pub const JMP_IF_TRUE: u8 = 0x0d; // PUSH_INT 1, JMP_IF_TRUE <address>, 1 is a t
 pub const JMP_IF_FALSE: u8 = 0x0e; // PUSH_INT 0, JMP_IF_FALSE <address>, 0 is a falsy value, if the popped value in the stack is != 0, it will not jump.
 pub const JMP_IF_TRUE_PEEK: u8 = 0x1a; // JMP_IF_TRUE_PEEK <address>, does the same thing as JMP_IF_TRUE, except the value is not popped.
 pub const JMP_IF_FALSE_PEEK: u8 = 0x1b; // JMP_IF_FALSE_PEEK <address>, does the same thing as JMP_IF_FALSE, except the value is not popped.
+pub const JMP_IF_NOT_NONE_PEEK: u8 = 0x1e; // JMP_IF_NOT_NONE_PEEK <address>, jumps if the value on top of the stack is not none, without popping it.
+pub const ASSERT: u8 = 0x1c; // ASSERT <line> <has message, 0|1> <source string>, pops a boolean (and the message string below it if there is one) and stops the program if it is false
 pub const CALL: u8 = 0x0f; // CALL <address>, it's similar to JMP, but it also saves the next opcode's IP to the call stack
 
 // FUNCTION RELATED
 
+pub const PUSH_FN: u8 = 0x29; // PUSH_FN <address> <argument count>, pushes a lambda that starts at the address
+pub const CALL_VALUE: u8 = 0x2a; // CALL_VALUE <argument count>, pops a lambda and its arguments and calls it, the arguments become its locals
+pub const LOAD_LOCAL: u8 = 0x2b; // LOAD_LOCAL 0, pushes a local of the current call (the first argument is 0)
+pub const STORE_LOCAL: u8 = 0x2f; // STORE_LOCAL 0, pops a value into a local of the current call
+
+// GENERATORS
+
+pub const GEN_START: u8 = 0x30; // GEN_START <local count>, the first instruction of a generator, returns a paused generator that continues after it instead of running the body
+pub const YIELD: u8 = 0x31; // YIELD, pops a value and pauses the generator, giving the value to the for loop that resumed it
+pub const GEN_END: u8 = 0x32; // GEN_END, the last instruction of a generator, it has no values left so the for loop that resumed it ends
+pub const GET_ITER: u8 = 0x33; // GET_ITER, pops a list or generator and pushes a generator that gives its items
+pub const ITER_NEXT: u8 = 0x34; // ITER_NEXT <address>, pops a generator and pushes its next value, or jumps to the address when it has none left
+
 pub const RET: u8 = 0x10; // RET (it changes the IP to the most recent one in the call stack)
+pub const RET_VOID: u8 = 0x3a; // RET_VOID, like RET for lambdas that give back nothing, values their statements left on the stack are thrown away
+
+// TASKS
+
+pub const SPAWN: u8 = 0x35; // SPAWN <argument count> <address>, pops a function and its arguments and starts a task that calls it, the task ends at the address once it returns
+pub const TASK_END: u8 = 0x36; // TASK_END, the task is done and the next one continues
+pub const MAKE_CHANNEL: u8 = 0x37; // MAKE_CHANNEL, pushes a new empty channel
+pub const SEND: u8 = 0x38; // SEND, pops a value and a channel, adds the value to the channel and lets the next task run
+pub const RECEIVE: u8 = 0x39; // RECEIVE, pops a channel and pushes its oldest value, the task waits while the channel is empty
 
 // MEMORY ACCESS (VARIABLE STORAGE)
 
 pub const LOAD: u8 = 0x13; // LOAD 0
 pub const STORE: u8 = 0x14; // STORE <TYPE> 0
 
+// OUTPUT
+
+pub const PRINT: u8 = 0x11; // PRINT <argument count>, pops that many values and writes them to the output seperated by spaces
+pub const PRINTLN: u8 = 0x12; // PRINTLN <argument count>, same as PRINT but ends with a new line
+
+// INPUT
+
+pub const READ_LINE: u8 = 0x1f; // READ_LINE, reads a line from the input (without the line ending) and pushes it, or none once the input is empty
+pub const PARSE_INT: u8 = 0x20; // PARSE_INT, pops a string and pushes the int it contains, or none if it isn't one
+pub const ARGS: u8 = 0x2c; // ARGS, pushes the list of arguments the program was given
+pub const ENV: u8 = 0x2d; // ENV, pops a name and pushes the value of that environment variable, or none if it isn't set
+pub const EXIT: u8 = 0x2e; // EXIT, pops an int and stops the program with it as the exit code
+pub const CALL_NATIVE: u8 = 0x21; // CALL_NATIVE <name> <argument count>, pops the arguments, calls the native function registered under the name and pushes what it returns
+
 // PROGRAM RELATED
 
 pub const HALT: u8 = 0x16; // HALT
\ No newline at end of file
@@ -0,0 +1,119 @@
+// HOST I/O FOR THE VM (where print and println write to, and the clock the time module reads)
+
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// An output sink that keeps everything written to it in memory, meant for embedding the VM and capturing a program's output in tests.
+///
+/// Clones share the same buffer, so keep a clone around before handing one to `VM::set_output`.
+#[derive(Clone, Default)]
+pub struct OutputBuffer {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns everything written so far as a string, invalid UTF-8 is replaced.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).to_string()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Where the time module gets the time from, set with `VM::set_clock`.
+pub trait Clock {
+    /// Milliseconds since the Unix epoch.
+    fn now_ms(&self) -> i64;
+
+    /// Nanoseconds since some point in the past that never goes backwards, only the difference between two readings means anything.
+    fn monotonic_ns(&self) -> i64;
+
+    /// Waits for `ms` milliseconds, which is never negative.
+    fn sleep(&mut self, ms: i64);
+}
+
+/// The clock of the OS, the one every VM uses by default.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        // a clock set before 1970 gives negative times instead of failing
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_millis() as i64,
+            Err(err) => -(err.duration().as_millis() as i64),
+        }
+    }
+
+    fn monotonic_ns(&self) -> i64 {
+        self.start.elapsed().as_nanos() as i64
+    }
+
+    fn sleep(&mut self, ms: i64) {
+        thread::sleep(Duration::from_millis(ms as u64));
+    }
+}
+
+/// A clock that only moves when the program sleeps or the host advances it, so every run of a program sees the same times.
+///
+/// It starts at `start_ms` (0 by default, the Unix epoch) with the monotonic time at 0, and sleeping returns immediately.
+/// Clones share the same time, so keep a clone around before handing one to `VM::set_clock` to advance it from the host.
+#[derive(Clone, Default)]
+pub struct FakeClock {
+    elapsed_ns: Rc<Cell<i64>>,
+    start_ms: i64,
+}
+
+impl FakeClock {
+    pub fn new(start_ms: i64) -> Self {
+        Self { elapsed_ns: Rc::new(Cell::new(0)), start_ms }
+    }
+
+    /// Moves the time forward by `ms` milliseconds.
+    pub fn advance(&self, ms: i64) {
+        self.elapsed_ns.set(self.elapsed_ns.get().saturating_add(ms.saturating_mul(1_000_000)));
+    }
+}
+
+impl Clock for FakeClock {
+    fn now_ms(&self) -> i64 {
+        self.start_ms.saturating_add(self.elapsed_ns.get() / 1_000_000)
+    }
+
+    fn monotonic_ns(&self) -> i64 {
+        self.elapsed_ns.get()
+    }
+
+    fn sleep(&mut self, ms: i64) {
+        self.advance(ms);
+    }
+}
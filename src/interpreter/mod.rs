@@ -1,2 +1,4 @@
 pub mod constants;
+pub mod io;
+pub mod value;
 pub mod vm;
\ No newline at end of file
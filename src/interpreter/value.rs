@@ -0,0 +1,270 @@
+// VALUES THE VM WORKS WITH, AND THEIR CONVERSIONS TO AND FROM RUST TYPES
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+/// A value on the VM's stack, this is what native functions take as arguments and return.
+///
+/// Native functions that are declared without a return type should return `Value::None`.
+///
+/// Rust values convert into it with `Value::from` (`Option`s become `Value::None` when empty, `Vec`s become lists),
+/// and back out with `try_into`, which fails with a message naming both types when the value has a different type.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    List(Vec<Value>),
+    Map(IndexMap<Key, Value>), // keeps the order the keys were first added in
+    Tuple(Vec<Value>),
+    Function { address: usize, arity: u8 }, // a lambda, where its code starts in the bytecode and how many arguments it takes
+    Generator(Rc<RefCell<Generator>>), // what calling a generator gives back, copies share it so a value taken by one is gone for all of them
+    Channel(Rc<RefCell<VecDeque<Value>>>), // the values sent to a channel that haven't been received yet, oldest first, copies share them
+    None
+}
+
+/// A map key, only ints, strs and bools can be keys (the checker makes sure of it) so they can be hashed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+}
+
+/// A paused generator, or the items a for loop over a list has left.
+#[derive(Debug)]
+pub struct Generator {
+    pub(crate) state: GeneratorState,
+}
+
+#[derive(Debug)]
+pub(crate) enum GeneratorState {
+    Suspended { ip: usize, locals: Vec<Value>, stack: Vec<Value> }, // where to continue, and the locals and stack of the generator's call
+    Running, // its body is running right now, so it can't be resumed again until it yields
+    Done,
+    Items(std::vec::IntoIter<Value>),
+}
+
+impl Value {
+    pub(crate) fn generator(state: GeneratorState) -> Value {
+        Value::Generator(Rc::new(RefCell::new(Generator { state })))
+    }
+
+    /// The name of the value's type, as it's written in Cobalt.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "str",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Tuple(_) => "tuple",
+            Value::Function { .. } => "fn",
+            Value::Generator(_) => "gen",
+            Value::Channel(_) => "channel",
+            Value::None => "none",
+        }
+    }
+}
+
+// how values look when they are printed
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            // {:?} keeps the fraction, so 2.0 doesn't print as 2
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::List(items) => {
+                write!(f, "[")?;
+                write_items(f, items.iter())?;
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_items(f, [&Value::from(key.clone())].into_iter())?;
+                    write!(f, ": ")?;
+                    write_items(f, [value].into_iter())?;
+                }
+                write!(f, "}}")
+            }
+            Value::Tuple(items) => {
+                write!(f, "(")?;
+                write_items(f, items.iter())?;
+                write!(f, ")")
+            }
+            Value::Function { .. } => write!(f, "<fn>"),
+            Value::Generator(_) => write!(f, "<gen>"),
+            Value::Channel(_) => write!(f, "<channel>"),
+            Value::None => write!(f, "none"),
+        }
+    }
+}
+
+// strings inside lists, maps and tuples are quoted, so ["a, b"] and ["a", "b"] look different
+fn write_items<'a>(f: &mut fmt::Formatter<'_>, items: impl Iterator<Item = &'a Value>) -> fmt::Result {
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        match item {
+            Value::Str(item) => write!(f, "{:?}", item)?,
+            item => write!(f, "{}", item)?,
+        }
+    }
+    Ok(())
+}
+
+// maps are equal when they have the same entries, no matter what order they were added in
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(l), Value::Int(r)) => l == r,
+            (Value::Float(l), Value::Float(r)) => l == r,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Str(l), Value::Str(r)) => l == r,
+            (Value::List(l), Value::List(r)) | (Value::Tuple(l), Value::Tuple(r)) => l == r,
+            (Value::Map(l), Value::Map(r)) => l == r,
+            (Value::Function { address: l, .. }, Value::Function { address: r, .. }) => l == r,
+            (Value::Generator(l), Value::Generator(r)) => Rc::ptr_eq(l, r),
+            (Value::Channel(l), Value::Channel(r)) => Rc::ptr_eq(l, r),
+            (Value::None, Value::None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Key> for Value {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Int(value) => Value::Int(value),
+            Key::Str(value) => Value::Str(value),
+            Key::Bool(value) => Value::Bool(value),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::None, Into::into)
+    }
+}
+
+pub(crate) fn mismatch(expected: &str, found: &Value) -> String {
+    format!("Expected a value of type '{}', but found a value of type '{}'.", expected, found.type_name())
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(value) => Ok(value),
+            other => Err(mismatch("int", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Float(value) => Ok(value),
+            other => Err(mismatch("float", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(value) => Ok(value),
+            other => Err(mismatch("bool", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Str(value) => Ok(value),
+            other => Err(mismatch("str", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Key {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(value) => Ok(Key::Int(value)),
+            Value::Str(value) => Ok(Key::Str(value)),
+            Value::Bool(value) => Ok(Key::Bool(value)),
+            other => Err(format!("Values of type '{}' can't be used as map keys.", other.type_name())),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::List(items) => Ok(items),
+            other => Err(mismatch("list", &other)),
+        }
+    }
+}
@@ -1,13 +1,25 @@
-use std::{collections::HashMap, process};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::io::{self, BufRead, Write};
+
+use indexmap::IndexMap;
 
 use super::constants;
-use crate::errors;
+use super::io::{Clock, SystemClock};
+use crate::errors::{self, Access, VmError};
+use crate::stdlib::{self, fs::Permissions, random::Rng};
+
+use super::value::{Generator, GeneratorState};
+pub use super::value::{Key, Value};
 
 #[derive(Debug)]
 pub enum Opcode {
     PushInt,
+    PushFloat,
     PushStr,
     PushBool,
+    PushNone,
     Pop,
     Add,
     Sub,
@@ -15,18 +27,51 @@ pub enum Opcode {
     Div,
     Mod,
     Neg,
+    ToFloat,
+    ToStr,
     Not,
     Eq,
     Neq,
     Lt,
     Gt,
+    Le,
+    Ge,
     Jmp,
     JmpIfTrue,
     JmpIfFalse,
     JmpIfTruePeek,
     JmpIfFalsePeek,
+    JmpIfNotNonePeek,
+    Assert,
+    Print,
+    Println,
+    ReadLine,
+    ParseInt,
+    Args,
+    Env,
+    Exit,
+    StoreLocal,
+    GenStart,
+    Yield,
+    GenEnd,
+    GetIter,
+    IterNext,
+    Spawn,
+    TaskEnd,
+    MakeChannel,
+    Send,
+    Receive,
+    CallNative,
+    MakeList,
+    MakeMap,
+    MakeTuple,
+    TupleGet,
+    PushFn,
+    CallValue,
+    LoadLocal,
     Call,
     Ret,
+    RetVoid,
     Load,
     Store,
     Halt,
@@ -36,8 +81,10 @@ impl Opcode {
     fn from_u8(byte: u8) -> Option<Self> {
         match byte {
             0x01 => Some(Opcode::PushInt),
+            0x22 => Some(Opcode::PushFloat),
             0x02 => Some(Opcode::PushStr),
             0x18 => Some(Opcode::PushBool),
+            0x1d => Some(Opcode::PushNone),
             0x03 => Some(Opcode::Pop),
             0x04 => Some(Opcode::Add),
             0x05 => Some(Opcode::Sub),
@@ -45,18 +92,51 @@ impl Opcode {
             0x07 => Some(Opcode::Div),
             0x15 => Some(Opcode::Mod),
             0x17 => Some(Opcode::Neg),
+            0x23 => Some(Opcode::ToFloat),
+            0x24 => Some(Opcode::ToStr),
             0x19 => Some(Opcode::Not),
             0x08 => Some(Opcode::Eq),
             0x09 => Some(Opcode::Neq),
             0x0a => Some(Opcode::Lt),
             0x0b => Some(Opcode::Gt),
+            0x3b => Some(Opcode::Le),
+            0x3c => Some(Opcode::Ge),
             0x0c => Some(Opcode::Jmp),
             0x0d => Some(Opcode::JmpIfTrue),
             0x0e => Some(Opcode::JmpIfFalse),
             0x1a => Some(Opcode::JmpIfTruePeek),
             0x1b => Some(Opcode::JmpIfFalsePeek),
+            0x1e => Some(Opcode::JmpIfNotNonePeek),
+            0x1c => Some(Opcode::Assert),
+            0x11 => Some(Opcode::Print),
+            0x12 => Some(Opcode::Println),
+            0x1f => Some(Opcode::ReadLine),
+            0x20 => Some(Opcode::ParseInt),
+            0x2c => Some(Opcode::Args),
+            0x2d => Some(Opcode::Env),
+            0x2e => Some(Opcode::Exit),
+            0x21 => Some(Opcode::CallNative),
+            0x25 => Some(Opcode::MakeList),
+            0x26 => Some(Opcode::MakeMap),
+            0x27 => Some(Opcode::MakeTuple),
+            0x28 => Some(Opcode::TupleGet),
+            0x29 => Some(Opcode::PushFn),
+            0x2a => Some(Opcode::CallValue),
+            0x2b => Some(Opcode::LoadLocal),
+            0x2f => Some(Opcode::StoreLocal),
+            0x30 => Some(Opcode::GenStart),
+            0x31 => Some(Opcode::Yield),
+            0x32 => Some(Opcode::GenEnd),
+            0x33 => Some(Opcode::GetIter),
+            0x34 => Some(Opcode::IterNext),
+            0x35 => Some(Opcode::Spawn),
+            0x36 => Some(Opcode::TaskEnd),
+            0x37 => Some(Opcode::MakeChannel),
+            0x38 => Some(Opcode::Send),
+            0x39 => Some(Opcode::Receive),
             0x0f => Some(Opcode::Call),
             0x10 => Some(Opcode::Ret),
+            0x3a => Some(Opcode::RetVoid),
             0x13 => Some(Opcode::Load),
             0x14 => Some(Opcode::Store),
             0x16 => Some(Opcode::Halt),
@@ -65,42 +145,216 @@ impl Opcode {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-enum Value {
-    Int(i64),
-    Bool(bool),
-    Str(String)
-}
-
-// TODO: add Ge and Le as options in the future
 enum CmpOp {
     Eq,
     Neq,
     Lt,
-    Gt
+    Gt,
+    Le,
+    Ge,
+}
+
+/// The signature of native functions, they get the arguments a program called them with and return a value or an error message.
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, String>;
+
+// natives in the standard library that need the VM itself, to call the lambdas a program passed them (list.map)
+pub(crate) type VmNativeFn = fn(&mut VM, Vec<Value>) -> Result<Value, VmError>;
+
+enum NativeFunction {
+    Host(Box<NativeFn>), // registered with VM::register_native
+    Vm(VmNativeFn),
+}
+
+struct Native {
+    arity: usize,
+    function: NativeFunction,
+}
+
+// CALL and CALL_VALUE push one of these, RET goes back to return_ip and throws away the locals of the call
+// ITER_NEXT pushes one when it resumes a generator, YIELD saves the generator's locals and stack back into it
+struct Frame {
+    return_ip: usize,
+    locals_base: usize, // where the call's locals (the arguments of a lambda) start in VM::locals
+    stack_base: usize, // how big the stack was when the call started
+    generator: Option<(Rc<RefCell<Generator>>, usize)>, // the generator that was resumed, and where the for loop goes once it has no values left
+}
+
+type Channel = Rc<RefCell<VecDeque<Value>>>;
+
+// a task that isn't running right now, everything it needs to continue where it left off, the globals are shared by every task
+struct Task {
+    ip: usize,
+    stack: Vec<Value>,
+    call_stack: Vec<Frame>,
+    locals: Vec<Value>,
+    waiting: Option<Channel>, // the channel it is receiving from, it can't continue until something is sent to it
+}
+
+impl Task {
+    fn can_run(&self) -> bool {
+        self.waiting.as_ref().is_none_or(|channel| !channel.borrow().is_empty())
+    }
 }
 
 pub struct VM {
     bytecode: Vec<u8>,
     ip: usize,
     stack: Vec<Value>,
-    call_stack: Vec<usize>,
+    call_stack: Vec<Frame>,
     global: HashMap<usize, Value>,
-    local: HashMap<usize, Value>,
+    locals: Vec<Value>, // the locals of every call on the call stack, LOAD_LOCAL is relative to the innermost one
+    output: Box<dyn Write>, // where PRINT and PRINTLN write to
+    input: Box<dyn BufRead>, // where READ_LINE reads from
+    natives: HashMap<String, Native>, // what CALL_NATIVE calls, by the name the program declared them with (extern fn name)
+    permissions: Permissions, // the directories the fs module may use
+    clock: Box<dyn Clock>, // where the time module gets the time from
+    rng: Rng, // where the random module gets its numbers from
+    args: Vec<String>, // what ARGS gives the program
+    allow_env: bool, // whether ENV may read environment variables
+    tasks: VecDeque<Task>, // every task except the running one, in the order they get to run again
+    scheduler: Option<Rng>, // picks the next task when seeded, otherwise they take turns
+    native_calls: usize, // how many lambdas natives are calling right now (list.map), tasks can't switch in the middle of one
     debug_mode: bool
 }
 
 impl VM {
+    /// Creates a VM for the bytecode, with the standard library's native functions already registered.
     pub fn new(bytecode: Vec<u8>, debug_mode: bool) -> VM {
-        Self {
+        let mut vm = Self {
             bytecode,
             ip: 0,
             stack: Vec::new(),
             call_stack: Vec::new(),
             global: HashMap::new(),
-            local: HashMap::new(),
+            locals: Vec::new(),
+            output: Box::new(io::stdout()),
+            input: Box::new(io::BufReader::new(io::stdin())), // not stdin().lock(), holding the lock would block every other VM in the process
+            natives: HashMap::new(),
+            permissions: Permissions::default(),
+            clock: Box::new(SystemClock::new()),
+            rng: Rng::from_time(),
+            args: Vec::new(),
+            allow_env: false,
+            tasks: VecDeque::new(),
+            scheduler: None,
+            native_calls: 0,
             debug_mode
-        }
+        };
+
+        stdlib::register(&mut vm);
+        vm
+    }
+
+    /// Change where the program's output goes, it goes to stdout by default.
+    ///
+    /// Use an `io::OutputBuffer` to capture the output when embedding the VM.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Change where the program's input comes from, it comes from stdin by default.
+    ///
+    /// Anything that implements `BufRead` works, such as `std::io::Cursor` for giving the program fixed input in tests.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.input = Box::new(input);
+    }
+
+    /// Change where the time module gets the time from, it uses the OS clock by default.
+    ///
+    /// Use an `io::FakeClock` to give the program the same times on every run.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Set the arguments the program gets from `args()`, it gets none by default.
+    pub fn set_args(&mut self, args: impl IntoIterator<Item = impl Into<String>>) {
+        self.args = args.into_iter().map(Into::into).collect();
+    }
+
+    /// Allow the program to read environment variables with `env(name)`, reading one is a `VmError::PermissionDenied` by default.
+    pub fn allow_env(&mut self) {
+        self.allow_env = true;
+    }
+
+    /// Seed the random module, so it gives the same numbers on every run (and every machine). It's seeded from the OS clock by default.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Seed the order tasks run in, the next task is picked at random from the ones that can run instead of them taking turns.
+    ///
+    /// The same seed always gives the same order, so a program can be tested with different orders and every failure can be repeated.
+    pub fn set_schedule_seed(&mut self, seed: u64) {
+        self.scheduler = Some(Rng::new(seed));
+    }
+
+    /// Make a Rust function callable from programs that declare it with `extern fn`.
+    ///
+    /// `name`: The name the program declares it with, `extern fn now(): int` calls the function registered as "now"
+    /// `arity`: How many arguments it takes, calls with any other amount are a runtime error
+    /// `function`: Gets the arguments in the order they were passed, returning `Err` stops the program with the message
+    ///
+    /// ```ignore
+    /// vm.register_native("now", 0, |_args| Ok(Value::Int(1700000000)));
+    /// ```
+    pub fn register_native(&mut self, name: &str, arity: usize, function: impl Fn(&[Value]) -> Result<Value, String> + 'static) {
+        self.natives.insert(name.to_string(), Native { arity, function: NativeFunction::Host(Box::new(function)) });
+    }
+
+    pub(crate) fn register_vm_native(&mut self, name: &str, arity: usize, function: VmNativeFn) {
+        self.natives.insert(name.to_string(), Native { arity, function: NativeFunction::Vm(function) });
+    }
+
+    /// Allow the program to read files inside `dir` (and its subdirectories) with the fs module, nothing can be read by default.
+    ///
+    /// Fails if `dir` doesn't exist.
+    pub fn allow_read(&mut self, dir: impl AsRef<std::path::Path>) -> io::Result<()> {
+        self.permissions.allow_read(dir)
+    }
+
+    /// Allow the program to write files inside `dir` (and its subdirectories) with the fs module, nothing can be written by default.
+    ///
+    /// Fails if `dir` doesn't exist.
+    pub fn allow_write(&mut self, dir: impl AsRef<std::path::Path>) -> io::Result<()> {
+        self.permissions.allow_write(dir)
+    }
+
+    pub(crate) fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    pub(crate) fn clock(&mut self) -> &mut dyn Clock {
+        self.clock.as_mut()
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    pub(crate) fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns the value of a global variable, `None` if it was never stored (or the program hasn't run yet).
+    ///
+    /// `id`: The variable's slot, `Codegen::globals` maps the names of top level variables to their slots
+    pub fn global(&self, id: usize) -> Option<&Value> {
+        self.global.get(&id)
+    }
+
+    /// Returns every global variable by slot.
+    pub fn globals(&self) -> &HashMap<usize, Value> {
+        &self.global
+    }
+
+    /// Returns what was left on the stack, the bottom of the stack comes first.
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    // a runtime error at the current instruction
+    pub(crate) fn error(&self, message: impl Into<String>) -> VmError {
+        VmError::Runtime { message: message.into(), ip: self.ip }
     }
 
     fn validate_bytecode(&mut self) -> bool {
@@ -121,74 +375,409 @@ impl VM {
         }
     }
 
-    fn fetch_byte(&mut self) -> u8 {
+    fn fetch_byte(&mut self) -> Result<u8, VmError> {
         if self.ip >= self.bytecode.len() {
-            errors::vm_err("Out of bounds access attempted! The VM was looking for an opcode but found nothing.", self.ip);
+            return Err(self.error("Out of bounds access attempted! The VM was looking for an opcode but found nothing."));
         }
         let byte = self.bytecode[self.ip];
         self.ip += 1;
-        byte
+        Ok(byte)
     }
 
-    fn fetch_u64(&mut self) -> u64 {
+    fn fetch_u64(&mut self) -> Result<u64, VmError> {
         if self.ip + 8 >= self.bytecode.len() {
-            errors::vm_err("Out of bounds access attempted! The VM was looking for a value but found nothing (or not enough bytes).", self.ip);
+            return Err(self.error("Out of bounds access attempted! The VM was looking for a value but found nothing (or not enough bytes)."));
         }
         let mut buf = [0u8; 8];
         buf.copy_from_slice(&self.bytecode[self.ip..self.ip + 8]);
         self.ip += 8;
-        u64::from_le_bytes(buf)
+        Ok(u64::from_le_bytes(buf))
     }
 
-    // pops two values from the stack and returns them as left and right, meant for binary operations
-    fn pop_two_stack(&mut self) -> (Value, Value) {
-        let right = self.stack.pop().unwrap_or_else(|| {
-            errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip);
-        });
-        let left = self.stack.pop().unwrap_or_else(|| {
-            errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip);
+    // fetches a u64 operand that is used as an address or a variable slot
+    fn fetch_usize(&mut self, what: &str) -> Result<usize, VmError> {
+        let value = self.fetch_u64()?;
+        value.try_into().map_err(|_| self.error(format!("Attempted to do {} operation, but converting the operand into a usize failed!", what)))
+    }
+
+    // strings are stored as <length byte> <utf-8 bytes>
+    fn fetch_str(&mut self) -> Result<String, VmError> {
+        let length = self.fetch_byte()? as usize;
+        if self.ip + length > self.bytecode.len() {
+            return Err(self.error("Out of bounds access attempted! The VM was looking for the bytes of a string but found nothing (or not enough bytes)."));
+        }
+
+        let bytes = &self.bytecode[self.ip..self.ip + length];
+        let value = String::from_utf8(bytes.to_vec()).map_err(|_| self.error("Attempted to read a string, but it is not valid UTF-8!"))?;
+        self.ip += length;
+        Ok(value)
+    }
+
+    fn pop(&mut self) -> Result<Value, VmError> {
+        self.stack.pop().ok_or_else(|| self.error(errors::VMERR_STACK_UNDERFLOW))
+    }
+
+    fn peek(&self) -> Result<&Value, VmError> {
+        self.stack.last().ok_or_else(|| self.error(errors::VMERR_STACK_UNDERFLOW))
+    }
+
+    // pops the top count values, in the order they were pushed
+    fn pop_many(&mut self, count: usize) -> Result<Vec<Value>, VmError> {
+        if count > self.stack.len() {
+            return Err(self.error(errors::VMERR_STACK_UNDERFLOW));
+        }
+
+        Ok(self.stack.split_off(self.stack.len() - count))
+    }
+
+    // pops the arguments of PRINT/PRINTLN and writes them out in the order they were pushed
+    fn print(&mut self, newline: bool) -> Result<(), VmError> {
+        let count = self.fetch_byte()? as usize;
+        let args = self.pop_many(count)?;
+
+        let mut text = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(" ");
+        if newline {
+            text.push('\n');
+        }
+
+        self.output.write_all(text.as_bytes()).map_err(|_| self.error("Failed to write to the output!"))
+    }
+
+    // reads a line without its line ending, none when the input has nothing left
+    fn read_line(&mut self) -> Result<Value, VmError> {
+        // make sure anything printed before (like a prompt) shows up before waiting
+        self.output.flush().map_err(|_| self.error("Failed to flush the output!"))?;
+
+        let mut line = String::new();
+        let read = self.input.read_line(&mut line).map_err(|_| self.error("Failed to read from the input!"))?;
+        if read == 0 {
+            return Ok(Value::None);
+        }
+
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+
+        Ok(Value::Str(line))
+    }
+
+    // pops the arguments, calls the native function and pushes its result
+    fn call_native(&mut self) -> Result<(), VmError> {
+        let name = self.fetch_str()?;
+        let count = self.fetch_byte()? as usize;
+
+        let Some(native) = self.natives.get(&name) else {
+            return Err(self.error(format!("Native function '{}' was called, but the host program never registered it.", name)));
+        };
+
+        if native.arity != count {
+            return Err(self.error(format!("Native function '{}' takes {} argument(s), but was called with {}.", name, native.arity, count)));
+        }
+
+        let args = self.pop_many(count)?;
+        let value = match &self.natives[&name].function {
+            NativeFunction::Host(function) => function(&args).map_err(|msg| self.error(format!("Native function '{}' failed: {}", name, msg)))?,
+            NativeFunction::Vm(function) => function(self, args)?,
+        };
+        self.stack.push(value);
+        Ok(())
+    }
+
+    // starts running a lambda, the arguments become its locals
+    fn enter(&mut self, function: &Value, args: Vec<Value>) -> Result<(), VmError> {
+        let Value::Function { address, arity } = *function else {
+            return Err(self.error(format!("Expected a function to call, but got type '{}'.", function.type_name())));
+        };
+
+        if arity as usize != args.len() {
+            return Err(self.error(format!("Function takes {} argument(s), but was called with {}.", arity, args.len())));
+        }
+
+        self.call_stack.push(Frame { return_ip: self.ip, locals_base: self.locals.len(), stack_base: self.stack.len(), generator: None });
+        self.locals.extend(args);
+        self.ip = address;
+        Ok(())
+    }
+
+    // GEN_START <local count>, runs right after a generator is called, instead of running its body it gives back a paused generator
+    fn gen_start(&mut self) -> Result<(), VmError> {
+        let local_count = self.fetch_usize("GEN_START")?;
+        let frame = self.call_stack.pop().ok_or_else(|| self.error("GEN_START was used outside of a generator!"))?;
+
+        // the arguments are the first locals, the rest are the variables the body declares
+        let mut locals = self.locals.split_off(frame.locals_base);
+        if locals.len() > local_count {
+            return Err(self.error(format!("Generator has {} locals, but was called with {} arguments.", local_count, locals.len())));
+        }
+        locals.resize(local_count, Value::None);
+
+        self.stack.push(Value::generator(GeneratorState::Suspended { ip: self.ip, locals, stack: Vec::new() }));
+        self.ip = frame.return_ip;
+        Ok(())
+    }
+
+    // ITER_NEXT <address>
+    fn iter_next(&mut self) -> Result<(), VmError> {
+        let exit = self.fetch_usize("ITER_NEXT")?;
+        let generator = match self.pop()? {
+            Value::Generator(generator) => generator,
+            other => return Err(self.error(format!("IterNext expected a generator, but got type '{}'.", other.type_name()))),
+        };
+
+        let state = std::mem::replace(&mut generator.borrow_mut().state, GeneratorState::Running);
+        match state {
+            GeneratorState::Items(mut items) => {
+                match items.next() {
+                    Some(item) => self.stack.push(item),
+                    None => self.ip = exit,
+                }
+                generator.borrow_mut().state = GeneratorState::Items(items);
+            }
+            GeneratorState::Suspended { ip, locals, stack } => {
+                // continue the generator's body as if it was called, YIELD or GEN_END come back here
+                self.call_stack.push(Frame {
+                    return_ip: self.ip,
+                    locals_base: self.locals.len(),
+                    stack_base: self.stack.len(),
+                    generator: Some((generator, exit)),
+                });
+                self.locals.extend(locals);
+                self.stack.extend(stack);
+                self.ip = ip;
+            }
+            GeneratorState::Done => {
+                generator.borrow_mut().state = GeneratorState::Done;
+                self.ip = exit;
+            }
+            GeneratorState::Running => return Err(self.error("A generator can't be looped over while it is running, a for loop inside of it is looping over itself.")),
+        }
+
+        Ok(())
+    }
+
+    // the innermost frame, which has to belong to a generator a for loop resumed, with the generator and where the loop ends
+    fn generator_frame(&mut self, what: &str) -> Result<(Frame, Rc<RefCell<Generator>>, usize), VmError> {
+        if let Some(mut frame) = self.call_stack.pop() && let Some((generator, exit)) = frame.generator.take() {
+            return Ok((frame, generator, exit));
+        }
+        Err(self.error(format!("{} was used outside of a generator!", what)))
+    }
+
+    // YIELD, pauses the generator with its locals and stack as they are, the value goes to the for loop
+    fn r#yield(&mut self) -> Result<(), VmError> {
+        let value = self.pop()?;
+        let (frame, generator, _) = self.generator_frame("YIELD")?;
+
+        let locals = self.locals.split_off(frame.locals_base);
+        let stack = self.stack.split_off(frame.stack_base);
+        generator.borrow_mut().state = GeneratorState::Suspended { ip: self.ip, locals, stack };
+
+        self.stack.push(value);
+        self.ip = frame.return_ip;
+        Ok(())
+    }
+
+    // GEN_END, the generator is done for good and the for loop that resumed it ends
+    fn gen_end(&mut self) -> Result<(), VmError> {
+        let (frame, generator, exit) = self.generator_frame("GEN_END")?;
+
+        self.locals.truncate(frame.locals_base);
+        self.stack.truncate(frame.stack_base);
+        generator.borrow_mut().state = GeneratorState::Done;
+
+        self.ip = exit;
+        Ok(())
+    }
+
+    /// Calls a lambda from Rust and runs it until it returns, this is how natives like `list.map` call the function they were given.
+    pub(crate) fn call_value(&mut self, function: &Value, args: Vec<Value>) -> Result<Value, VmError> {
+        let depth = self.call_stack.len();
+        self.enter(function, args)?;
+
+        self.native_calls += 1;
+        let result = self.run_call(depth);
+        self.native_calls -= 1;
+
+        result?;
+        self.pop()
+    }
+
+    fn run_call(&mut self, depth: usize) -> Result<(), VmError> {
+        while self.call_stack.len() > depth {
+            if self.step()?.is_some() {
+                return Err(self.error("The program halted while a function was still running."));
+            }
+        }
+        Ok(())
+    }
+
+    // SPAWN <argument count> <address>, the task starts out as a call of the function that returns to the address
+    fn spawn(&mut self) -> Result<(), VmError> {
+        let count = self.fetch_byte()? as usize;
+        let task_end = self.fetch_usize("SPAWN")?;
+        let function = self.pop()?;
+        let args = self.pop_many(count)?;
+
+        let Value::Function { address, arity } = function else {
+            return Err(self.error(format!("Expected a function to spawn, but got type '{}'.", function.type_name())));
+        };
+        if arity as usize != args.len() {
+            return Err(self.error(format!("Function takes {} argument(s), but was spawned with {}.", arity, args.len())));
+        }
+
+        self.tasks.push_back(Task {
+            ip: address,
+            stack: Vec::new(),
+            call_stack: vec![Frame { return_ip: task_end, locals_base: 0, stack_base: 0, generator: None }],
+            locals: args,
+            waiting: None,
         });
+        Ok(())
+    }
 
-        (left, right)
+    // takes the next task that can run out of the queue, the first one in line or a random one when the scheduler is seeded
+    fn next_task(&mut self) -> Option<Task> {
+        let ready: Vec<usize> = self.tasks.iter().enumerate().filter(|(_, task)| task.can_run()).map(|(i, _)| i).collect();
+        let index = match &mut self.scheduler {
+            _ if ready.is_empty() => return None,
+            Some(rng) => ready[rng.below(ready.len() as u64) as usize],
+            None => ready[0],
+        };
+        self.tasks.remove(index)
+    }
+
+    // continues the task, the running one is given back so it can be put in the queue (or dropped when it's done)
+    fn resume(&mut self, task: Task) -> Task {
+        Task {
+            ip: std::mem::replace(&mut self.ip, task.ip),
+            stack: std::mem::replace(&mut self.stack, task.stack),
+            call_stack: std::mem::replace(&mut self.call_stack, task.call_stack),
+            locals: std::mem::replace(&mut self.locals, task.locals),
+            waiting: None,
+        }
     }
 
-    // gets type name from a Value
-    fn get_type_name(&self, val: &Value) -> &str {
-        match val {
-            Value::Int(_) => "int",
-            Value::Bool(_) => "bool",
-            Value::Str(_) => "str",
+    // lets the next task run, the running one goes to the back of the queue, waiting on the channel if there is one
+    // without a task to switch to it just keeps going, unless it's waiting, then no task can ever continue
+    fn switch_task(&mut self, waiting: Option<Channel>) -> Result<(), VmError> {
+        if self.native_calls > 0 {
+            return match waiting {
+                Some(_) => Err(self.error("A lambda called by a standard library function can't wait to receive from a channel, the channel was empty.")),
+                None => Ok(()),
+            };
         }
+
+        let Some(next) = self.next_task() else {
+            return match waiting {
+                Some(_) => Err(self.error(errors::VMERR_DEADLOCK)),
+                None => Ok(()),
+            };
+        };
+
+        let mut current = self.resume(next);
+        current.waiting = waiting;
+        self.tasks.push_back(current);
+        Ok(())
     }
 
-    // once strings come, this function will not apply for ADD opcodes
+    // used by HALT and EXIT, the tasks that can still run get to finish first, the ones waiting on a channel nothing will send to are dropped
+    // the running task comes back to the same instruction once it's its turn again
+    fn finish_other_tasks(&mut self) -> bool {
+        if self.native_calls > 0 {
+            return false;
+        }
+        let Some(next) = self.next_task() else {
+            return false;
+        };
+
+        let mut current = self.resume(next);
+        current.ip -= 1;
+        self.tasks.push_back(current);
+        true
+    }
+
+    // TASK_END, the running task is done, so one that is waiting in the queue continues
+    fn task_end(&mut self) -> Result<(), VmError> {
+        // the code that started the program is always one of the tasks until it halts, so there's someone left to continue
+        let next = self.next_task().ok_or_else(|| self.error(errors::VMERR_DEADLOCK))?;
+        self.resume(next);
+        Ok(())
+    }
+
+    // RECEIVE, a task that finds the channel empty waits and tries again once something was sent to it
+    fn receive(&mut self) -> Result<(), VmError> {
+        let channel = match self.pop()? {
+            Value::Channel(channel) => channel,
+            other => return Err(self.error(format!("Receive expected a channel, but got type '{}'.", other.type_name()))),
+        };
+
+        let value = channel.borrow_mut().pop_front();
+        match value {
+            Some(value) => self.stack.push(value),
+            None => {
+                self.stack.push(Value::Channel(channel.clone()));
+                self.ip -= 1; // RECEIVE has no operands, so this runs it again
+                self.switch_task(Some(channel))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn local_slot(&mut self, what: &str) -> Result<usize, VmError> {
+        let slot = self.fetch_usize(what)?;
+        let base = self.call_stack.last().map_or(0, |frame| frame.locals_base);
+        if base + slot >= self.locals.len() {
+            return Err(self.error(format!("Tried to use local variable '{}' that does not exist!", slot)));
+        }
+        Ok(base + slot)
+    }
+
+    // pops two values from the stack and returns them as left and right, meant for binary operations
+    fn pop_two_stack(&mut self) -> Result<(Value, Value), VmError> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+
+        Ok((left, right))
+    }
+
+    // ADD is handled on its own, since it also works on strings
     // for op_int, 1 = division, 2 = modulus, 0 = everything else, used because it's quicker than comparing op name for errors
-    fn binary_int_op<F>(&mut self, op: F, op_name: &str, op_int: u8)
+    // dividing floats by zero is not an error, it gives infinity or NaN like IEEE 754 says
+    fn binary_num_op<F, G>(&mut self, op: F, float_op: G, op_name: &str, op_int: u8) -> Result<(), VmError>
     where
-        F: Fn(i64, i64) -> i64
+        F: Fn(i64, i64) -> Option<i64>,
+        G: Fn(f64, f64) -> f64
     {
-        let (left, right) = self.pop_two_stack();
+        let (left, right) = self.pop_two_stack()?;
 
         match (&left, &right) {
             (Value::Int(l), Value::Int(r)) => {
                 if op_int == 1 && *r == 0 {
-                    errors::vm_err("Cannot divide by zero.", self.ip);
+                    return Err(self.error("Cannot divide by zero."));
                 } else if op_int == 2 && *r == 0 {
-                    errors::vm_err("Cannot perform modulus by zero.", self.ip);
+                    return Err(self.error("Cannot perform modulus by zero."));
                 }
 
-                self.stack.push(Value::Int(op(*l, *r)));
+                match op(*l, *r) {
+                    Some(value) => self.stack.push(Value::Int(value)),
+                    None => return Err(self.error(format!("Integer overflow in {}.", op_name))),
+                }
+                Ok(())
             }
-            _ => {
-                let err = format!("Mismatched or unsupported types on {} operation of type '{}' and '{}'.", op_name, self.get_type_name(&left), self.get_type_name(&right));
-                errors::vm_err(&err, self.ip);
+            (Value::Float(l), Value::Float(r)) => {
+                self.stack.push(Value::Float(float_op(*l, *r)));
+                Ok(())
             }
+            _ => Err(self.error(format!("Mismatched or unsupported types on {} operation of type '{}' and '{}'.", op_name, left.type_name(), right.type_name()))),
         }
     }
 
 
-    fn binary_cmp_op(&mut self, op: CmpOp, op_name: &str) {
-        let (left, right) = self.pop_two_stack();
+    fn binary_cmp_op(&mut self, op: CmpOp, op_name: &str) -> Result<(), VmError> {
+        let (left, right) = self.pop_two_stack()?;
  
         let result = match (&left, &right, op) {
             (Value::Int(l), Value::Int(r), op) => match op {
@@ -196,190 +785,397 @@ impl VM {
                 CmpOp::Neq => Value::Bool(l != r),
                 CmpOp::Lt => Value::Bool(l < r),
                 CmpOp::Gt => Value::Bool(l > r),
+                CmpOp::Le => Value::Bool(l <= r),
+                CmpOp::Ge => Value::Bool(l >= r),
+            },
+            // every comparison with NaN is false, except !=
+            (Value::Float(l), Value::Float(r), op) => match op {
+                CmpOp::Eq => Value::Bool(l == r),
+                CmpOp::Neq => Value::Bool(l != r),
+                CmpOp::Lt => Value::Bool(l < r),
+                CmpOp::Gt => Value::Bool(l > r),
+                CmpOp::Le => Value::Bool(l <= r),
+                CmpOp::Ge => Value::Bool(l >= r),
             },
             (Value::Bool(l), Value::Bool(r), CmpOp::Eq) => Value::Bool(l == r),
             (Value::Bool(l), Value::Bool(r), CmpOp::Neq) => Value::Bool(l != r),
+            (Value::Str(l), Value::Str(r), CmpOp::Eq) => Value::Bool(l == r),
+            (Value::Str(l), Value::Str(r), CmpOp::Neq) => Value::Bool(l != r),
+            // lists, maps, tuples and functions are compared as a whole
+            (Value::List(_), Value::List(_), CmpOp::Eq) | (Value::Map(_), Value::Map(_), CmpOp::Eq) | (Value::Tuple(_), Value::Tuple(_), CmpOp::Eq) | (Value::Function { .. }, Value::Function { .. }, CmpOp::Eq) | (Value::Generator(_), Value::Generator(_), CmpOp::Eq) => Value::Bool(left == right),
+            (Value::List(_), Value::List(_), CmpOp::Neq) | (Value::Map(_), Value::Map(_), CmpOp::Neq) | (Value::Tuple(_), Value::Tuple(_), CmpOp::Neq) | (Value::Function { .. }, Value::Function { .. }, CmpOp::Neq) | (Value::Generator(_), Value::Generator(_), CmpOp::Neq) => Value::Bool(left != right),
+            // optionals can be compared with none and with values of their inner type
+            (Value::None, r, CmpOp::Eq) => Value::Bool(*r == Value::None),
+            (l, Value::None, CmpOp::Eq) => Value::Bool(*l == Value::None),
+            (Value::None, r, CmpOp::Neq) => Value::Bool(*r != Value::None),
+            (l, Value::None, CmpOp::Neq) => Value::Bool(*l != Value::None),
             _ => {
                 // TODO: change this error in the future, it flows kind of weird
                 // this error needs to be more specific about what kind of operation was done and what type that is not supported on
                 // example: VM Error: ">" is not a supported operation for type bool.
-                let err = format!("Mismatched or unsupported types on {} operation of type '{}' and '{}'.", op_name, self.get_type_name(&left), self.get_type_name(&right));
-                errors::vm_err(&err, self.ip);
+                return Err(self.error(format!("Mismatched or unsupported types on {} operation of type '{}' and '{}'.", op_name, left.type_name(), right.type_name())));
             }
         };
 
         self.stack.push(result);
+        Ok(())
+    }
+
+    // pops the condition of a conditional jump, jumps are only taken on bools
+    fn pop_condition(&mut self, opcode_name: &str) -> Result<bool, VmError> {
+        match self.pop()? {
+            Value::Bool(condition) => Ok(condition),
+            other => Err(self.error(format!("{} expected a boolean condition, but got type '{}'.", opcode_name, other.type_name()))),
+        }
     }
 
-    pub fn interpret(&mut self) {
+    // like pop_condition, but leaves the condition on the stack
+    fn peek_condition(&self, opcode_name: &str) -> Result<bool, VmError> {
+        match self.peek()? {
+            Value::Bool(condition) => Ok(*condition),
+            other => Err(self.error(format!("{} expected a boolean condition, but got type '{}'.", opcode_name, other.type_name()))),
+        }
+    }
+
+    fn assert(&mut self) -> Result<(), VmError> {
+        let line = self.fetch_u64()?;
+        let has_message = self.fetch_byte()? != 0;
+        let source = self.fetch_str()?;
+        let condition = self.pop_condition("Assert")?;
+        let message = if has_message {
+            match self.pop()? {
+                Value::Str(message) => Some(message),
+                other => return Err(self.error(format!("Assert expected a message of type 'str', but got type '{}'.", other.type_name()))),
+            }
+        } else {
+            None
+        };
+
+        if condition {
+            Ok(())
+        } else {
+            Err(VmError::AssertionFailed { message, source, line })
+        }
+    }
+
+    // EXIT, only codes a process can exit with are allowed, and not the ones cobalt uses for its own failures
+    fn exit(&mut self) -> Result<(), VmError> {
+        let code = match self.pop()? {
+            Value::Int(code) => code,
+            other => return Err(self.error(format!("Exit expected a value of type 'int', but got type '{}'.", other.type_name()))),
+        };
+
+        let reserved = errors::EXIT_COMPILE_ERROR..=errors::EXIT_ASSERTION_FAILED;
+        let code = match i32::try_from(code) {
+            Ok(code) if (0..=255).contains(&code) && !reserved.contains(&code) => code,
+            _ => {
+                return Err(self.error(format!(
+                    "Can't exit with code {}, exit codes have to be between 0 and 255 and {} to {} are reserved for errors.",
+                    code, reserved.start(), reserved.end()
+                )));
+            }
+        };
+
+        self.output.flush().map_err(|_| self.error("Failed to flush the output!"))?;
+        Err(VmError::Exit { code })
+    }
+
+    /// Run the bytecode until it halts.
+    ///
+    /// Returns the value on top of the stack when the program halted (`Value::None` if the stack was empty),
+    /// `VM::stack` has the rest and `VM::global` has the variables. Errors (including failed asserts) stop the program and are returned instead of exiting the process.
+    /// A program that calls `exit(code)` or has a `main` returns `VmError::Exit` with its code.
+    pub fn interpret(&mut self) -> Result<Value, VmError> {
         if !self.validate_bytecode() {
-            errors::vm_err("Not a valid bytecode file!", 4); // give a specific ip because this error just applies to the start of the file's first 4 bytes
+            // give a specific ip because this error just applies to the start of the file's first 4 bytes
+            return Err(VmError::Runtime { message: "Not a valid bytecode file!".to_string(), ip: 4 });
         }
 
         loop {
-            let opcode = self.fetch_byte();
-            match Opcode::from_u8(opcode) {
-                Some(Opcode::PushInt) => {
-                    let value = self.fetch_u64();
-                    self.stack.push(Value::Int(value.try_into().unwrap()));
-                }
-                Some(Opcode::PushBool) => {
-                    let value = self.fetch_byte();
+            if let Some(value) = self.step()? {
+                return Ok(value);
+            }
+        }
+    }
 
-                    if value == 0 {
-                        self.stack.push(Value::Bool(false))
-                    } else {
-                        self.stack.push(Value::Bool(true))
-                    }
-                }
-                Some(Opcode::Pop) => {
-                    self.stack.pop().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-                },
-                Some(Opcode::Add) => {
-                    let (left, right) = self.pop_two_stack();
-                    
-                    match (left, right) {
-                        (Value::Int(left_val), Value::Int(right_val)) => {
-                            self.stack.push(Value::Int(left_val + right_val));
-                        }
-                        (Value::Str(left_str), Value::Str(right_str)) => {
-                            self.stack.push(Value::Str(format!("{}{}", left_str, right_str)));
-                        }
-                        _ => {
-                            errors::vm_err("Mismatched types on an addition operation!", self.ip);
+    // runs a single instruction, gives back the value the program ended with once it halts
+    fn step(&mut self) -> Result<Option<Value>, VmError> {
+        let opcode = self.fetch_byte()?;
+        match Opcode::from_u8(opcode) {
+            Some(Opcode::PushInt) => {
+                let value = self.fetch_u64()?;
+                self.stack.push(Value::Int(value as i64)); // the generator writes negative values in two's complement
+            }
+            Some(Opcode::PushFloat) => {
+                let value = self.fetch_u64()?;
+                self.stack.push(Value::Float(f64::from_bits(value)));
+            }
+            Some(Opcode::PushStr) => {
+                let value = self.fetch_str()?;
+                self.stack.push(Value::Str(value));
+            }
+            Some(Opcode::PushNone) => self.stack.push(Value::None),
+            Some(Opcode::PushBool) => {
+                let value = self.fetch_byte()?;
+                self.stack.push(Value::Bool(value != 0));
+            }
+            Some(Opcode::Pop) => {
+                self.pop()?;
+            },
+            Some(Opcode::Add) => {
+                let (left, right) = self.pop_two_stack()?;
+                
+                match (left, right) {
+                    (Value::Int(left_val), Value::Int(right_val)) => {
+                        match left_val.checked_add(right_val) {
+                            Some(value) => self.stack.push(Value::Int(value)),
+                            None => return Err(self.error("Integer overflow in addition.")),
                         }
                     }
-                }
-                Some(Opcode::Sub) => self.binary_int_op(|a , b| a - b, "subtraction", 0),
-                Some(Opcode::Mul) => self.binary_int_op(|a, b| a * b, "multiplication", 0),
-                Some(Opcode::Div) => self.binary_int_op(|a, b| a / b, "division", 1),
-                Some(Opcode::Mod) => self.binary_int_op(|a, b| a % b, "modulus", 2),
-                Some(Opcode::Neg) => {
-                    let value = self.stack.pop().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-
-                    match value {
-                        Value::Int(val) => {
-                            self.stack.push(Value::Int(0 - val));
-                        }
-                        _ => {
-                            errors::vm_err("Unsupported type for NEG operation, only numbers can be turned into negative values.", self.ip);
-                        }
+                    (Value::Float(left_val), Value::Float(right_val)) => {
+                        self.stack.push(Value::Float(left_val + right_val));
                     }
-                }
-                Some(Opcode::Not) => {
-                    let value = self.stack.pop().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-
-                    match value {
-                        Value::Bool(val) => {
-                            if val {
-                                self.stack.push(Value::Bool(false));
-                            } else {
-                                self.stack.push(Value::Bool(true));
-                            }
-                        }
-                        _ => {
-                            errors::vm_err("Cannot apply NOT operation on a value that is not a boolean!", self.ip);
-                        }
+                    (Value::Str(mut left_str), Value::Str(right_str)) => {
+                        // append to the string that was popped instead of building a new one
+                        left_str.push_str(&right_str);
+                        self.stack.push(Value::Str(left_str));
+                    }
+                    _ => {
+                        return Err(self.error("Mismatched types on an addition operation!"));
                     }
                 }
-                Some(Opcode::Eq) => self.binary_cmp_op(CmpOp::Eq, "=="),
-                Some(Opcode::Neq) => self.binary_cmp_op(CmpOp::Neq, "!="),
-                Some(Opcode::Lt) => self.binary_cmp_op(CmpOp::Lt, "<"),
-                Some(Opcode::Gt) => self.binary_cmp_op(CmpOp::Gt, ">"),
-                Some(Opcode::Jmp) => {
-                    self.ip = self.fetch_u64().try_into().unwrap_or_else(|_| errors::vm_err("Attempted to do JMP operation, but converting the address into a usize failed!", self.ip));
+            }
+            Some(Opcode::Sub) => self.binary_num_op(i64::checked_sub, |a, b| a - b, "subtraction", 0)?,
+            Some(Opcode::Mul) => self.binary_num_op(i64::checked_mul, |a, b| a * b, "multiplication", 0)?,
+            Some(Opcode::Div) => self.binary_num_op(i64::checked_div, |a, b| a / b, "division", 1)?,
+            Some(Opcode::Mod) => self.binary_num_op(i64::checked_rem, |a, b| a % b, "modulus", 2)?,
+            Some(Opcode::Neg) => {
+                match self.pop()? {
+                    Value::Int(val) => match val.checked_neg() {
+                        Some(val) => self.stack.push(Value::Int(val)),
+                        None => return Err(self.error("Integer overflow in negation.")),
+                    },
+                    Value::Float(val) => self.stack.push(Value::Float(-val)),
+                    _ => return Err(self.error("Unsupported type for NEG operation, only numbers can be turned into negative values.")),
                 }
-                Some(Opcode::JmpIfTrue) => {
-                    let address = self.fetch_u64();
-                    let condition = self.stack.pop().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-                    match condition {
-                        Value::Bool(true) => {
-                            self.ip = address.try_into().unwrap_or_else(|_| errors::vm_err("Converting address to usize failed!", self.ip));
-                        }
-                        Value::Bool(false) => { /* do nothing */ }
-                        _ => {
-                            let err = format!("JmpIfTrue expected a boolean condition, but got type '{}'.", self.get_type_name(&condition));
-                            errors::vm_err(&err, self.ip);
-                        }
-                    }
+            }
+            Some(Opcode::ToFloat) => {
+                match self.pop()? {
+                    Value::Int(val) => self.stack.push(Value::Float(val as f64)),
+                    Value::Float(val) => self.stack.push(Value::Float(val)),
+                    other => return Err(self.error(format!("ToFloat expected a number, but got type '{}'.", other.type_name()))),
                 }
-                Some(Opcode::JmpIfFalse) => {
-                    let address = self.fetch_u64();
-                    let condition = self.stack.pop().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-                    match condition {
-                        Value::Bool(false) => {
-                            self.ip = address.try_into().unwrap_or_else(|_| errors::vm_err("Converting address to usize failed!", self.ip));
-                        }
-                        Value::Bool(true) => { /* do nothing */ }
-                        _ => {
-                            let err = format!("JmpIfFalse expected a boolean condition, but got type '{}'.", self.get_type_name(&condition));
-                            errors::vm_err(&err, self.ip);
-                        }
-                    }
+            }
+            Some(Opcode::ToStr) => {
+                let value = self.pop()?;
+                let text = match value {
+                    Value::Str(text) => text,
+                    other => other.to_string(),
+                };
+                self.stack.push(Value::Str(text));
+            }
+            Some(Opcode::Not) => {
+                match self.pop()? {
+                    Value::Bool(val) => self.stack.push(Value::Bool(!val)),
+                    _ => return Err(self.error("Cannot apply NOT operation on a value that is not a boolean!")),
                 }
-                Some(Opcode::JmpIfTruePeek) => {
-                    let address = self.fetch_u64();
-                    let condition = self.stack.last().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-                    match condition {
-                        Value::Bool(true) => {
-                            self.ip = address.try_into().unwrap_or_else(|_| errors::vm_err("Converting address to usize failed!", self.ip));
-                        }
-                        Value::Bool(false) => { /* do nothing */ }
-                        _ => {
-                            let err = format!("JmpIfTruePeek expected a boolean condition, but got type '{}'.", self.get_type_name(condition));
-                            errors::vm_err(&err, self.ip);
-                        }
-                    }
+            }
+            Some(Opcode::Eq) => self.binary_cmp_op(CmpOp::Eq, "==")?,
+            Some(Opcode::Neq) => self.binary_cmp_op(CmpOp::Neq, "!=")?,
+            Some(Opcode::Lt) => self.binary_cmp_op(CmpOp::Lt, "<")?,
+            Some(Opcode::Gt) => self.binary_cmp_op(CmpOp::Gt, ">")?,
+            Some(Opcode::Le) => self.binary_cmp_op(CmpOp::Le, "<=")?,
+            Some(Opcode::Ge) => self.binary_cmp_op(CmpOp::Ge, ">=")?,
+            Some(Opcode::Jmp) => {
+                self.ip = self.fetch_usize("JMP")?;
+            }
+            Some(Opcode::JmpIfTrue) => {
+                let address = self.fetch_usize("JMP_IF_TRUE")?;
+                if self.pop_condition("JmpIfTrue")? {
+                    self.ip = address;
                 }
-                Some(Opcode::JmpIfFalsePeek) => {
-                    let address = self.fetch_u64();
-                    let condition = self.stack.last().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip));
-                    match condition {
-                        Value::Bool(false) => {
-                            self.ip = address.try_into().unwrap_or_else(|_| errors::vm_err("Converting address to usize failed!", self.ip));
-                        }
-                        Value::Bool(true) => { /* do nothing */ }
-                        _ => {
-                            let err = format!("JmpIfFalsePeek expected a boolean condition, but got type '{}'.", self.get_type_name(condition));
-                            errors::vm_err(&err, self.ip);
-                        }
-                    }
+            }
+            Some(Opcode::JmpIfFalse) => {
+                let address = self.fetch_usize("JMP_IF_FALSE")?;
+                if !self.pop_condition("JmpIfFalse")? {
+                    self.ip = address;
+                }
+            }
+            Some(Opcode::JmpIfTruePeek) => {
+                let address = self.fetch_usize("JMP_IF_TRUE_PEEK")?;
+                if self.peek_condition("JmpIfTruePeek")? {
+                    self.ip = address;
                 }
-                Some(Opcode::Call) => {
-                    let address: usize = self.fetch_u64().try_into().unwrap_or_else(|_| errors::vm_err("Attempted to do CALL operation, but converting the address into a usize failed!", self.ip));
-                    self.call_stack.push(self.ip);
+            }
+            Some(Opcode::JmpIfFalsePeek) => {
+                let address = self.fetch_usize("JMP_IF_FALSE_PEEK")?;
+                if !self.peek_condition("JmpIfFalsePeek")? {
                     self.ip = address;
                 }
-                Some(Opcode::Ret) => {
-                    let address = self.call_stack.pop().unwrap_or_else(|| errors::vm_err("Call stack underflow! RET operation failed.", self.ip));
+            }
+            Some(Opcode::JmpIfNotNonePeek) => {
+                let address = self.fetch_usize("JMP_IF_NOT_NONE_PEEK")?;
+                if *self.peek()? != Value::None {
                     self.ip = address;
                 }
-                Some(Opcode::Load) => {
-                    let index: usize = self.fetch_u64().try_into().unwrap_or_else(|_| errors::vm_err("Attempted to do LOAD operation, but converting the variable name into a usize failed!", self.ip));
-                    let value = self.global.get(&index).unwrap_or_else(|| errors::vm_err(format!("Tried to load variable at index '{}' that does not exist!", &index).as_str(), self.ip));
-                    self.stack.push(value.clone());
+            }
+            Some(Opcode::Assert) => self.assert()?,
+            Some(Opcode::Print) => self.print(false)?,
+            Some(Opcode::Println) => self.print(true)?,
+            Some(Opcode::ReadLine) => {
+                let line = self.read_line()?;
+                self.stack.push(line);
+            }
+            Some(Opcode::ParseInt) => {
+                match self.pop()? {
+                    Value::Str(text) => self.stack.push(text.trim().parse::<i64>().map_or(Value::None, Value::Int)),
+                    other => return Err(self.error(format!("ParseInt expected a value of type 'str', but got type '{}'.", other.type_name()))),
                 }
-                Some(Opcode::Store) => {
-                    let index: usize = self.fetch_u64().try_into().unwrap_or_else(|_| errors::vm_err("Attempted to do STORE operation, but converting the variable name into a usize failed!", self.ip));
-                    self.global.insert(index, self.stack.pop().unwrap_or_else(|| errors::vm_err(errors::VMERR_STACK_UNDERFLOW, self.ip)));
+            }
+            Some(Opcode::Args) => self.stack.push(Value::from(self.args.clone())),
+            Some(Opcode::Env) => {
+                let name = match self.pop()? {
+                    Value::Str(name) => name,
+                    other => return Err(self.error(format!("Env expected a value of type 'str', but got type '{}'.", other.type_name()))),
+                };
+                if !self.allow_env {
+                    return Err(VmError::PermissionDenied { access: Access::Env, path: name, ip: self.ip });
                 }
-                Some(Opcode::Halt) => {
-                    if self.debug_mode {
-                        println!("DEBUG: Process halted! Halt-time statistics printing:");
-                        println!("DEBUG: Stack: {:#?}", self.stack);
-                        println!("DEBUG: Global variable stack: {:#?}", self.global);
-                    }
+                // a value that isn't valid unicode is still given, with the invalid parts replaced
+                let value = std::env::var_os(&name).map(|value| value.to_string_lossy().into_owned());
+                self.stack.push(Value::from(value));
+            }
+            Some(Opcode::Exit) => {
+                if self.finish_other_tasks() {
+                    return Ok(None);
+                }
+                self.exit()?
+            }
+            Some(Opcode::CallNative) => self.call_native()?,
+            Some(Opcode::MakeList) => {
+                let count = self.fetch_usize("MAKE_LIST")?;
+                let items = self.pop_many(count)?;
+                self.stack.push(Value::List(items));
+            }
+            Some(Opcode::MakeMap) => {
+                let count = self.fetch_usize("MAKE_MAP")?;
+                let items = self.pop_many(count.checked_mul(2).ok_or_else(|| self.error(errors::VMERR_STACK_UNDERFLOW))?)?;
 
-                    process::exit(0);
+                // a key that shows up again replaces the value, but keeps its place
+                let mut entries = IndexMap::with_capacity(count);
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    let key = Key::try_from(key).map_err(|message| self.error(message))?;
+                    entries.insert(key, value);
                 }
-                None => {
-                    let err = format!("Expected opcode, received: {:x}, at IP: {}", opcode, self.ip);
-                    errors::vm_err(&err, self.ip);
+                self.stack.push(Value::Map(entries));
+            }
+            Some(Opcode::MakeTuple) => {
+                let count = self.fetch_byte()? as usize;
+                let items = self.pop_many(count)?;
+                self.stack.push(Value::Tuple(items));
+            }
+            Some(Opcode::TupleGet) => {
+                let index = self.fetch_byte()? as usize;
+                match self.pop()? {
+                    Value::Tuple(mut items) if index < items.len() => self.stack.push(items.swap_remove(index)),
+                    other => return Err(self.error(format!("TupleGet can't get item {} of a value of type '{}'.", index, other.type_name()))),
                 }
-                _ => {
-                    todo!("Unimplemented opcode: {:x}", opcode);
+            }
+            Some(Opcode::PushFn) => {
+                let address = self.fetch_usize("PUSH_FN")?;
+                let arity = self.fetch_byte()?;
+                self.stack.push(Value::Function { address, arity });
+            }
+            Some(Opcode::CallValue) => {
+                let count = self.fetch_byte()? as usize;
+                let function = self.pop()?;
+                let args = self.pop_many(count)?;
+                self.enter(&function, args)?;
+            }
+            Some(Opcode::LoadLocal) => {
+                let slot = self.local_slot("LOAD_LOCAL")?;
+                self.stack.push(self.locals[slot].clone());
+            }
+            Some(Opcode::StoreLocal) => {
+                // the variables of a lambda's block get their local the first time they are stored
+                let slot = self.fetch_usize("STORE_LOCAL")?;
+                let slot = self.call_stack.last().map_or(0, |frame| frame.locals_base) + slot;
+                if slot >= self.locals.len() {
+                    self.locals.resize(slot + 1, Value::None);
                 }
+                self.locals[slot] = self.pop()?;
+            }
+            Some(Opcode::GenStart) => self.gen_start()?,
+            Some(Opcode::Yield) => self.r#yield()?,
+            Some(Opcode::GenEnd) => self.gen_end()?,
+            Some(Opcode::GetIter) => {
+                let iterator = match self.pop()? {
+                    Value::List(items) => Value::generator(GeneratorState::Items(items.into_iter())),
+                    generator @ Value::Generator(_) => generator,
+                    other => return Err(self.error(format!("Only lists and generators can be looped over, but got type '{}'.", other.type_name()))),
+                };
+                self.stack.push(iterator);
+            }
+            Some(Opcode::IterNext) => self.iter_next()?,
+            Some(Opcode::Spawn) => self.spawn()?,
+            Some(Opcode::TaskEnd) => self.task_end()?,
+            Some(Opcode::MakeChannel) => self.stack.push(Value::Channel(Rc::new(RefCell::new(VecDeque::new())))),
+            Some(Opcode::Send) => {
+                let value = self.pop()?;
+                match self.pop()? {
+                    Value::Channel(channel) => channel.borrow_mut().push_back(value),
+                    other => return Err(self.error(format!("Send expected a channel, but got type '{}'.", other.type_name()))),
+                }
+                self.switch_task(None)?;
+            }
+            Some(Opcode::Receive) => self.receive()?,
+            Some(Opcode::Call) => {
+                let address = self.fetch_usize("CALL")?;
+                self.call_stack.push(Frame { return_ip: self.ip, locals_base: self.locals.len(), stack_base: self.stack.len(), generator: None });
+                self.ip = address;
+            }
+            Some(Opcode::Ret) => {
+                let frame = self.call_stack.pop().ok_or_else(|| self.error("Call stack underflow! RET operation failed."))?;
+                self.ip = frame.return_ip;
+                self.locals.truncate(frame.locals_base);
+            }
+            Some(Opcode::RetVoid) => {
+                let frame = self.call_stack.pop().ok_or_else(|| self.error("Call stack underflow! RET_VOID operation failed."))?;
+                self.ip = frame.return_ip;
+                self.locals.truncate(frame.locals_base);
+                self.stack.truncate(frame.stack_base);
+            }
+            Some(Opcode::Load) => {
+                let index = self.fetch_usize("LOAD")?;
+                let value = self.global.get(&index).ok_or_else(|| self.error(format!("Tried to load variable at index '{}' that does not exist!", index)))?;
+                self.stack.push(value.clone());
+            }
+            Some(Opcode::Store) => {
+                let index = self.fetch_usize("STORE")?;
+                let value = self.pop()?;
+                self.global.insert(index, value);
+            }
+            Some(Opcode::Halt) => {
+                if self.finish_other_tasks() {
+                    return Ok(None);
+                }
+
+                if self.debug_mode {
+                    println!("DEBUG: Process halted! Halt-time statistics printing:");
+                    println!("DEBUG: Stack: {:#?}", self.stack);
+                    println!("DEBUG: Global variable stack: {:#?}", self.global);
+                }
+
+                self.output.flush().map_err(|_| self.error("Failed to flush the output!"))?;
+
+                return Ok(Some(self.stack.last().cloned().unwrap_or(Value::None)));
+            }
+            None => {
+                return Err(self.error(format!("Expected opcode, received: {:x}, at IP: {}", opcode, self.ip)));
             }
         }
+
+        Ok(None)
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,5 @@
 use phf::phf_map;
-use std::process;
+use crate::errors::{Diagnostic, Stage};
 
 use super::tokens::{Token, TokenType};
 
@@ -7,23 +7,41 @@ static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "let" => TokenType::Let,
     "const" => TokenType::Const,
     "fn" => TokenType::Fn,
+    "extern" => TokenType::Extern,
     "return" => TokenType::Return,
     "true" => TokenType::True,
     "false" => TokenType::False,
+    "none" => TokenType::None,
     "if" => TokenType::If,
     "else" => TokenType::Else,
+    "assert" => TokenType::Assert,
+    "for" => TokenType::For,
+    "in" => TokenType::In,
+    "yield" => TokenType::Yield,
+    "spawn" => TokenType::Spawn,
+    "defer" => TokenType::Defer,
+    "type" => TokenType::Type,
+    "newtype" => TokenType::Newtype,
+    "int" => TokenType::TypeInt,
+    "bool" => TokenType::TypeBool,
+    "str" => TokenType::TypeStr,
+    "float" => TokenType::TypeFloat,
 };
 
 pub struct Lexer {
     pub src: Vec<char>,
     pos: usize,
+    line: usize,
+    col: usize,
 }
 
 impl Lexer {
     pub fn new(src: Vec<char>) -> Lexer {
         Self {
             src,
-            pos: 0
+            pos: 0,
+            line: 1,
+            col: 1
         }
     }
 
@@ -35,13 +53,27 @@ impl Lexer {
         self.src[self.pos]
     }
 
+    // the character after the current one, used to tell 1.5 apart from a member access
+    fn peek_next(&self) -> char {
+        self.src.get(self.pos + 1).copied().unwrap_or('\0')
+    }
+
     fn read(&mut self) -> char {
         let ch = self.peek();
         self.pos += 1;
+
+        if ch == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+
         ch
     }
 
-    pub fn lex(&mut self) -> Vec<Token> {
+    /// Turns the source into tokens, ending with an EOF token. Stops at the first error.
+    pub fn lex(&mut self) -> Result<Vec<Token>, Diagnostic> {
         lex_fn(self)
     }
 }
@@ -66,11 +98,12 @@ fn is_alphanumeric(ch: char) -> bool {
 
 // LEXER FUNCTION BELOW (wrapped by the lex method in the Lexer struct)
 
-fn lex_fn(l: &mut Lexer) -> Vec<Token> {
+fn lex_fn(l: &mut Lexer) -> Result<Vec<Token>, Diagnostic> {
     let mut tokens: Vec<Token> = vec![];
 
     while l.pos < l.src.len() {
         let ch = l.peek();
+        let (line, col) = (l.line, l.col); // where the current token starts
 
         // end when sentinel character appears
         if ch == '\0' {
@@ -79,22 +112,32 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
 
         match ch {
             '(' => {
-                tokens.push(Token { value: "(".to_string(), r#type: TokenType::OpenParen });
+                tokens.push(Token { value: "(".to_string(), r#type: TokenType::OpenParen, line, col });
                 l.read();
             }
 
             ')' => {
-                tokens.push(Token { value: ")".to_string(), r#type: TokenType::CloseParen });
+                tokens.push(Token { value: ")".to_string(), r#type: TokenType::CloseParen, line, col });
                 l.read();
             }
 
             '{' => {
-                tokens.push(Token { value: "{".to_string(), r#type: TokenType::OpenBrace });
+                tokens.push(Token { value: "{".to_string(), r#type: TokenType::OpenBrace, line, col });
                 l.read();
             }
 
             '}' => {
-                tokens.push(Token { value: "}".to_string(), r#type: TokenType::CloseBrace });
+                tokens.push(Token { value: "}".to_string(), r#type: TokenType::CloseBrace, line, col });
+                l.read();
+            }
+
+            '[' => {
+                tokens.push(Token { value: "[".to_string(), r#type: TokenType::OpenBracket, line, col });
+                l.read();
+            }
+
+            ']' => {
+                tokens.push(Token { value: "]".to_string(), r#type: TokenType::CloseBracket, line, col });
                 l.read();
             }
 
@@ -102,10 +145,10 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read();
                 if l.peek() == '=' {
                     let value: String = ">=".to_string();
-                    tokens.push(Token { value, r#type: TokenType::GreaterThanEqual });
+                    tokens.push(Token { value, r#type: TokenType::GreaterThanEqual, line, col });
                     l.read();
                 } else {
-                    tokens.push(Token { value: ">".to_string(), r#type: TokenType::GreaterThan });
+                    tokens.push(Token { value: ">".to_string(), r#type: TokenType::GreaterThan, line, col });
                 }
             }
 
@@ -113,10 +156,10 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read();
                 if l.peek() == '=' {
                     let value: String = "<=".to_string();
-                    tokens.push(Token { value, r#type: TokenType::LessThanEqual });
+                    tokens.push(Token { value, r#type: TokenType::LessThanEqual, line, col });
                     l.read();
                 } else {
-                    tokens.push(Token { value: "<".to_string(), r#type: TokenType::LessThan });
+                    tokens.push(Token { value: "<".to_string(), r#type: TokenType::LessThan, line, col });
                 }
             }
 
@@ -124,10 +167,10 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read();
                 if l.peek() == '=' {
                     let value: String = "+=".to_string();
-                    tokens.push(Token { value, r#type: TokenType::PlusEquals });
+                    tokens.push(Token { value, r#type: TokenType::PlusEquals, line, col });
                     l.read();
                 } else {
-                    tokens.push(Token { value: "+".to_string(), r#type: TokenType::Plus });
+                    tokens.push(Token { value: "+".to_string(), r#type: TokenType::Plus, line, col });
                 }
             }
 
@@ -135,25 +178,48 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read();
                 if l.peek() == '=' {
                     let value: String = "-=".to_string();
-                    tokens.push(Token { value, r#type: TokenType::MinusEquals });
+                    tokens.push(Token { value, r#type: TokenType::MinusEquals, line, col });
                     l.read();
                 } else {
-                    tokens.push(Token { value: "-".to_string(), r#type: TokenType::Minus });
+                    tokens.push(Token { value: "-".to_string(), r#type: TokenType::Minus, line, col });
                 }
             }
 
             '/' => {
-                tokens.push(Token { value: "/".to_string(), r#type: TokenType::Slash });
                 l.read();
+                if l.peek() == '*' {
+                    // block comments nest, so commenting out code that already has a block comment in it works
+                    l.read();
+                    let mut depth = 1;
+
+                    while depth > 0 {
+                        match l.read() {
+                            '/' if l.peek() == '*' => {
+                                l.read();
+                                depth += 1;
+                            }
+                            '*' if l.peek() == '/' => {
+                                l.read();
+                                depth -= 1;
+                            }
+                            '\0' => {
+                                return Err(Diagnostic::error(Stage::Lexer, "Block comment is never closed, expected '*/'.").at(line, col));
+                            }
+                            _ => {}
+                        }
+                    }
+                } else {
+                    tokens.push(Token { value: "/".to_string(), r#type: TokenType::Slash, line, col });
+                }
             }
 
             '*' => {
-                tokens.push(Token { value: "*".to_string(), r#type: TokenType::Star });
+                tokens.push(Token { value: "*".to_string(), r#type: TokenType::Star, line, col });
                 l.read();
             }
 
             '%' => {
-                tokens.push(Token { value: "%".to_string(), r#type: TokenType::Percent });
+                tokens.push(Token { value: "%".to_string(), r#type: TokenType::Percent, line, col });
                 l.read();
             }
 
@@ -161,11 +227,10 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read();
                 if l.peek() == '|' {
                     let value: String = "||".to_string();
-                    tokens.push(Token { value, r#type: TokenType::Or });
+                    tokens.push(Token { value, r#type: TokenType::Or, line, col });
                     l.read();
                 } else {
-                    eprintln!("Lexer Error: Expected a double '||' symbol, but only received a single '|' !");
-                    process::exit(1);
+                    return Err(Diagnostic::error(Stage::Lexer, "Expected a double '||' symbol, but only received a single '|' !").at(line, col));
                 }
             }
 
@@ -173,45 +238,113 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read();
                 if l.peek() == '&' {
                     let value: String = "&&".to_string();
-                    tokens.push(Token { value, r#type: TokenType::And });
+                    tokens.push(Token { value, r#type: TokenType::And, line, col });
                     l.read();
                 } else {
-                    eprintln!("Lexer Error: Expected a double '&&' symbol, but only received a single '&' !");
-                    process::exit(1);
+                    return Err(Diagnostic::error(Stage::Lexer, "Expected a double '&&' symbol, but only received a single '&' !").at(line, col));
                 }
             }
 
             '#' => {
+                l.read();
+                let doc = l.peek() == '#';
+                let mut text = String::new();
+
                 while l.peek() != '\n' && l.peek() != '\0' {
-                    l.read();
+                    text.push(l.read());
+                }
+
+                // doc comments (##) are kept so the parser can attach them to the declaration below, regular comments are thrown away
+                if doc {
+                    let text = text[1..].strip_prefix(' ').unwrap_or(&text[1..]).trim_end().to_string();
+                    tokens.push(Token { value: text, r#type: TokenType::DocComment, line, col });
                 }
+
                 if l.peek() == '\n' {
                     l.read(); // skip past it so we don't need to handle it later
                 }
             }
 
+            '?' => {
+                l.read();
+                if l.peek() == '?' {
+                    tokens.push(Token { value: "??".to_string(), r#type: TokenType::QuestionQuestion, line, col });
+                    l.read();
+                } else {
+                    tokens.push(Token { value: "?".to_string(), r#type: TokenType::Question, line, col });
+                }
+            }
+
             '=' => {
                 l.read();
                 if l.peek() == '=' {
-                    tokens.push(Token { value: "==".to_string(), r#type: TokenType::EqualsEquals });
+                    tokens.push(Token { value: "==".to_string(), r#type: TokenType::EqualsEquals, line, col });
+                    l.read();
+                } else if l.peek() == '>' {
+                    tokens.push(Token { value: "=>".to_string(), r#type: TokenType::FatArrow, line, col });
                     l.read();
                 } else {
-                    tokens.push(Token { value: "=".to_string(), r#type: TokenType::Equals });
+                    tokens.push(Token { value: "=".to_string(), r#type: TokenType::Equals, line, col });
                 }
             }
 
             '!' => {
                 l.read();
                 if l.peek() == '=' {
-                    tokens.push(Token { value: "!=".to_string(), r#type: TokenType::NotEqual });
+                    tokens.push(Token { value: "!=".to_string(), r#type: TokenType::NotEqual, line, col });
                     l.read();
                 } else {
-                    tokens.push(Token { value: "!".to_string(), r#type: TokenType::Not });
+                    tokens.push(Token { value: "!".to_string(), r#type: TokenType::Not, line, col });
                 }
             }
 
             ':' => {
-                tokens.push(Token { value: ch.to_string(), r#type: TokenType::Colon });
+                tokens.push(Token { value: ch.to_string(), r#type: TokenType::Colon, line, col });
+                l.read();
+            }
+
+            '@' => {
+                tokens.push(Token { value: ch.to_string(), r#type: TokenType::At, line, col });
+                l.read();
+            }
+
+            '"' => {
+                l.read(); // eat the opening quote
+                let mut string = String::new();
+
+                loop {
+                    match l.read() {
+                        '"' => break,
+                        '\\' => {
+                            let escaped = match l.read() {
+                                'n' => '\n',
+                                't' => '\t',
+                                'r' => '\r',
+                                '"' => '"',
+                                '\\' => '\\',
+                                other => {
+                                    return Err(Diagnostic::error(Stage::Lexer, format!("Unknown escape sequence '\\{}' in string.", other)).at(l.line, l.col - 1));
+                                }
+                            };
+                            string.push(escaped);
+                        }
+                        '\0' => {
+                            return Err(Diagnostic::error(Stage::Lexer, "String is never closed, expected '\"'.").at(line, col));
+                        }
+                        other => string.push(other),
+                    }
+                }
+
+                tokens.push(Token { value: string, r#type: TokenType::String, line, col });
+            }
+
+            ',' => {
+                tokens.push(Token { value: ch.to_string(), r#type: TokenType::Comma, line, col });
+                l.read();
+            }
+
+            '.' => {
+                tokens.push(Token { value: ch.to_string(), r#type: TokenType::Dot, line, col });
                 l.read();
             }
 
@@ -219,14 +352,43 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 l.read(); // just skip it
             }
 
+            // 255, 0xFF, 0b1111_1111, 0o377
+            // the literal is kept as written, the parser turns it into a value once it knows if it's negative
             ch if is_digit(ch) => {
                 let mut num: String = "".to_string();
 
-                while is_digit(l.peek()) {
+                while is_alphanumeric(l.peek()) {
                     num.push(l.read());
                 }
 
-                tokens.push(Token { value: num, r#type: TokenType::Number });
+                let (radix, digits) = match num.get(..2) {
+                    Some("0x" | "0X") => (16, &num[2..]),
+                    Some("0b" | "0B") => (2, &num[2..]),
+                    Some("0o" | "0O") => (8, &num[2..]),
+                    _ => (10, num.as_str()),
+                };
+
+                if !digits.chars().any(|digit| digit != '_') {
+                    return Err(Diagnostic::error(Stage::Lexer, format!("Numeric literal '{}' has no digits.", num)).at(line, col));
+                }
+
+                if let Some(invalid) = digits.chars().find(|digit| *digit != '_' && !digit.is_digit(radix)) {
+                    return Err(Diagnostic::error(Stage::Lexer, format!("Invalid digit '{}' in base {} numeric literal '{}'.", invalid, radix, num)).at(line, col));
+                }
+
+                // 1.5 and 1_000.25 are floats, only decimal literals can have a fraction
+                if radix == 10 && l.peek() == '.' && is_digit(l.peek_next()) {
+                    num.push(l.read()); // the dot
+
+                    while is_digit(l.peek()) || l.peek() == '_' {
+                        num.push(l.read());
+                    }
+
+                    tokens.push(Token { value: num, r#type: TokenType::Float, line, col });
+                    continue;
+                }
+
+                tokens.push(Token { value: num, r#type: TokenType::Number, line, col });
             }
 
             // IDENTIFIERS AND KEYWORDS HANDLED IN THIS BLOCK!!!
@@ -241,23 +403,27 @@ fn lex_fn(l: &mut Lexer) -> Vec<Token> {
                 // get the tokentype from the keywords
                 tokens.push(Token { 
                     value: ident, 
-                    r#type: keyword_type
+                    r#type: keyword_type,
+                    line,
+                    col
                 });
                 } else {
                     // default to identifier
                     tokens.push(Token { 
                         value: ident, 
-                        r#type: TokenType::Identifier 
+                        r#type: TokenType::Identifier,
+                        line,
+                        col
                     });
                 }
             }
 
             _ => {
-                panic!("LEXER ERROR: Unrecognized character in source: {}", ch);
+                return Err(Diagnostic::error(Stage::Lexer, format!("Unrecognized character in source: {}", ch)).at(line, col));
             }
         }
     }
 
-    tokens.push(Token { value: "EOF".to_string(), r#type: TokenType::EOF });
-    tokens
+    tokens.push(Token { value: "EOF".to_string(), r#type: TokenType::EOF, line: l.line, col: l.col });
+    Ok(tokens)
 }
\ No newline at end of file
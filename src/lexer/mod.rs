@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod lexer;
 pub mod tokens;
\ No newline at end of file
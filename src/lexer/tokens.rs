@@ -2,18 +2,30 @@
 pub enum TokenType {
     // TYPES
     Number,
+    Float, // 1.5
     Identifier, // mycoolvarorfunctionname
 	String, // "my cool string"
+    DocComment, // ## documents the declaration below it
 
     // KEYWORDS
     Let, // let myvar = 1 (mutable)
 	Const, // const myvar = 1 (immutable)
 	Fn, // fn mycoolfn(arg: string) {}
+    Extern, // extern fn now(): int (a function that the host program provides)
     Return, // return mycoolvalue
     If,
     Else,
+    Assert, // assert x == 5, "x should be 5"
+    For, // for x in xs {}
+    In,
+    Yield, // yield x (gives the next value of a generator)
+    Spawn, // spawn fn() { ... } (runs a function as a task of its own)
+    Defer, // defer println("done") (runs when the block it's in ends)
     True,
     False,
+    None, // the value of an optional (str?) that has nothing in it
+    Type, // type UserId = int
+    Newtype, // newtype Meters(int)
 
     // TYPES FOR VARIABLES (reserved because static typing is a strong feature of cobalt)
     // All types are 64-bit, 32-bit is a thing of the past
@@ -24,6 +36,9 @@ pub enum TokenType {
 
     // Operators and Symbols
     Colon, // :  
+    Comma, // ,
+    Dot, // . (math.sqrt)
+    At, // @ (starts an attribute, @deprecated)
     Plus, // +
     Minus, // -
     Star, // *
@@ -39,17 +54,24 @@ pub enum TokenType {
     MinusEquals, // -=
     Or, // ||
     And, // &&
+    QuestionQuestion, // ?? (gives the right hand side when the left hand side is none)
+    Question, // ? (after a type, makes it optional)
     Equals, // =
+    FatArrow, // => (between the parameters and the body of a lambda)
     Not, // ! (when found alone by itself, meant for unary expressions)
     OpenParen, // (
     CloseParen, // )
     OpenBrace, // {
     CloseBrace, // }
+    OpenBracket, // [
+    CloseBracket, // ]
     EOF, // pushed at end of tokenization
 }
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub value: String,
-    pub r#type: TokenType
+    pub r#type: TokenType,
+    pub line: usize, // where the token starts in the source, both start at 1
+    pub col: usize,
 }
\ No newline at end of file
@@ -1,6 +1,11 @@
 pub mod lexer;
 pub mod parser;
+pub mod typechecker;
 pub mod interpreter;
 pub mod codegen;
+pub mod compiler;
+pub mod stdlib;
 pub mod utils;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+
+pub use compiler::{compile, CompileOptions};
@@ -1,25 +1,58 @@
+use crate::typechecker::types::Type;
+
+/// How many levels deep statements, expressions and types can be nested.
+///
+/// The parser rejects anything deeper, and the checker and generator check it again for ASTs that were built by hand,
+/// so deeply nested code is an error instead of overflowing the stack.
+pub const MAX_DEPTH: usize = 256;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     Program,             // the node that contains the AST
     VariableDeclaration, // let x = 42, const x = 42 for immutable vars
+    TypeAlias,           // type UserId = int, another name for an existing type
+    NewtypeDeclaration,  // newtype Meters(int), a distinct type that is represented by an existing type at runtime
+    ExternFunctionDeclaration, // extern fn now(): int, a function that the program embedding the VM provides
+    AssertStatement,     // assert x == 5, "message", stops the program when the test is false
     IfStatement,         // if true {} else if x {} else {}, it checks the condition and if evaluated to true executes the statement following it.
+    ForStatement,        // for x in xs {}, runs the body once for every item of a list or value of a generator
+    YieldStatement,      // yield x, gives the next value of a generator and pauses it until the value after that is needed
+    SpawnStatement,      // spawn fn() { ... } or spawn worker(x), runs the call as a task of its own, the arguments are evaluated right away
+    DeferStatement,      // defer x, runs the expression when the block it's in ends, the last one deferred runs first
     BlockStatement,      // { body }, blocks have their own scope
     BinaryExpr,          // an expression which has a left and right hand side seperated by an operator that determines the operation
-    LogicalExpr,         // an expression which has a left and right hand side seperated by an operator, either and (&&) or or (||).
+    LogicalExpr,         // an expression which has a left and right hand side seperated by an operator, either and (&&), or (||) or the none fallback (??). The right hand side is only evaluated when needed.
     Identifier,          // a name used to identify variables and functions declared by the user
     NumericLiteral,      // 123
-    FloatLiteral,        // 123.0 (NOT IMPLEMENTED)
+    FloatLiteral,        // 123.0
     BooleanLiteral,      // true / false
-    StringLiteral,       // "content here" (NOT IMPLEMENTED)
+    NoneLiteral,         // none
+    StringLiteral,       // "content here"
+    Attribute,           // @deprecated("use y instead"), extra information attached to a declaration
     AssignmentExpr,      // x = 42
     UnaryExpr,           // -42, !true
+    CallExpr,            // Meters(5), int(distance)
+    MemberExpr,          // math.PI, a member of a builtin module, or pair.0, an item of a tuple
+    ListLiteral,         // [1, 2, 3]
+    MapLiteral,          // {"a": 1, "b": 2}
+    TupleLiteral,        // (1, "a")
+    Lambda,              // fn(x: int): int => x * 2, a function without a name that can be passed around as a value
+    ChannelExpr,         // channel<int>(), a new channel that tasks can send values of that type through
 }
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Program(Program),
     VariableDeclaration(VariableDeclaration),
+    TypeAlias(TypeAlias),
+    NewtypeDeclaration(NewtypeDeclaration),
+    ExternFunctionDeclaration(ExternFunctionDeclaration),
     IfStatement(IfStatement),
+    ForStatement(ForStatement),
+    YieldStatement(YieldStatement),
+    SpawnStatement(SpawnStatement),
+    DeferStatement(DeferStatement),
+    AssertStatement(AssertStatement),
     BlockStatement(BlockStatement),
     Expr(Expr),
 }
@@ -30,9 +63,19 @@ pub enum Expr {
     LogicalExpr(LogicalExpr),
     Identifier(Identifier),
     NumericLiteral(NumericLiteral),
+    FloatLiteral(FloatLiteral),
     BooleanLiteral(BooleanLiteral),
+    StringLiteral(StringLiteral),
+    NoneLiteral(NoneLiteral),
     AssignmentExpr(AssignmentExpr),
     UnaryExpr(UnaryExpr),
+    Call(CallExpr),
+    Member(MemberExpr),
+    ListLiteral(ListLiteral),
+    MapLiteral(MapLiteral),
+    TupleLiteral(TupleLiteral),
+    Lambda(Lambda),
+    ChannelExpr(ChannelExpr),
 }
 
 // === AST Node Definitions ===
@@ -48,7 +91,55 @@ pub struct VariableDeclaration {
     pub kind: NodeType,
     pub identifier: String,
     pub constant: bool,
+    pub r#type: Option<Type>, // the annotated type (let x: int = 5) after parsing, always filled in with the inferred type after checking
     pub value: Expr,
+    pub doc: Option<String>, // the ## doc comment right above the declaration, one line per comment line
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeAlias {
+    pub kind: NodeType,
+    pub identifier: String,
+    pub r#type: Type,
+    pub doc: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewtypeDeclaration {
+    pub kind: NodeType,
+    pub identifier: String,
+    pub inner: Type,
+    pub doc: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternFunctionDeclaration {
+    pub kind: NodeType,
+    pub identifier: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Type, // void when it's left out
+    pub doc: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+// name: type
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub r#type: Type,
+}
+
+// @name or @name(args), the parser accepts any name and arguments, the checker validates the ones it knows about
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub kind: NodeType,
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub line: usize,
+    pub col: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -59,6 +150,42 @@ pub struct IfStatement {
     pub body: Box<Stmt> // either a single statement or a block statement are most common
 }
 
+#[derive(Debug, Clone)]
+pub struct ForStatement {
+    pub kind: NodeType,
+    pub variable: String,
+    pub iterable: Expr,
+    pub body: Box<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct YieldStatement {
+    pub kind: NodeType,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnStatement {
+    pub kind: NodeType,
+    pub function: Expr,
+    pub args: Vec<Expr>, // empty for spawn fn() { ... }, which is spawned without calling it first
+}
+
+#[derive(Debug, Clone)]
+pub struct DeferStatement {
+    pub kind: NodeType,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssertStatement {
+    pub kind: NodeType,
+    pub test: Expr,
+    pub message: Option<Expr>,
+    pub source: String, // the test as it was written, so it can be shown when the assertion fails
+    pub line: usize,
+}
+
 // { code in here }
 #[derive(Debug, Clone)]
 pub struct BlockStatement {
@@ -94,12 +221,29 @@ pub struct NumericLiteral {
     pub value: i64,
 }
 
+#[derive(Debug, Clone)]
+pub struct FloatLiteral {
+    pub kind: NodeType,
+    pub value: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct BooleanLiteral {
     pub kind: NodeType,
     pub value: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct StringLiteral {
+    pub kind: NodeType,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NoneLiteral {
+    pub kind: NodeType,
+}
+
 #[derive(Debug, Clone)]
 pub struct AssignmentExpr {
     pub kind: NodeType,
@@ -114,3 +258,57 @@ pub struct UnaryExpr {
     pub operator: String, // +, -, ! (for if statements)
     pub value: Box<Expr>
 }
+
+#[derive(Debug, Clone)]
+pub struct CallExpr {
+    pub kind: NodeType,
+    pub callee: Box<Expr>,
+    pub args: Vec<Expr>,
+}
+
+// object.property
+#[derive(Debug, Clone)]
+pub struct MemberExpr {
+    pub kind: NodeType,
+    pub object: Box<Expr>,
+    pub property: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListLiteral {
+    pub kind: NodeType,
+    pub items: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MapLiteral {
+    pub kind: NodeType,
+    pub entries: Vec<(Expr, Expr)>, // key, value in the order they were written
+}
+
+#[derive(Debug, Clone)]
+pub struct TupleLiteral {
+    pub kind: NodeType,
+    pub items: Vec<Expr>, // always at least 2, (x) is just x in parenthesis
+}
+
+// fn(x: int): int => x * 2, fn(n: int): gen<int> { yield n } for a generator, or fn(x: int) { println(x) } when nothing is given back
+#[derive(Debug, Clone)]
+pub struct Lambda {
+    pub kind: NodeType,
+    pub params: Vec<Parameter>,
+    pub return_type: Option<Type>, // inferred from the body when it's left out
+    pub body: LambdaBody,
+}
+
+#[derive(Debug, Clone)]
+pub enum LambdaBody {
+    Expr(Box<Expr>), // => x * 2, the value is what the lambda gives back
+    Block(BlockStatement), // { yield 1 }, only generators and lambdas that give back nothing have one
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelExpr {
+    pub kind: NodeType,
+    pub r#type: Type, // the type of the values sent through it
+}
@@ -1,2 +1,3 @@
 pub mod ast;
+#[allow(clippy::module_inception)]
 pub mod parser;
\ No newline at end of file
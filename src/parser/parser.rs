@@ -1,18 +1,32 @@
 use crate::parser::ast;
 use crate::lexer::tokens::{Token, TokenType};
+use crate::typechecker::types::Type;
 
 use super::ast::VariableDeclaration;
 
-use std::process;
+use crate::errors::{Diagnostic, Stage};
 
 pub struct Parser {
     tokens: Vec<Token>,
-    pos: usize
+    pos: usize,
+    depth: usize, // how deeply the code being parsed is nested, see nest
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Self { tokens, pos: 0 }
+        Self { tokens, pos: 0, depth: 0 }
+    }
+
+    // every statement, expression and type goes one level deeper, deeper code is an error instead of running out of stack here or in the stages after this one
+    // operators in a chain (1 + 2 + 3) and calls in a chain (f()()) each wrap what came before them, so they go one level deeper each too
+    // callers put self.depth back once they are done with the level
+    fn nest(&mut self) -> Result<(), Diagnostic> {
+        if self.depth == ast::MAX_DEPTH {
+            let token = self.at();
+            return Err(Diagnostic::error(Stage::Parser, format!("The code is nested too deeply, it can be at most {} levels deep.", ast::MAX_DEPTH)).at(token.line, token.col));
+        }
+        self.depth += 1;
+        Ok(())
     }
 
     fn not_eof(&self) -> bool {
@@ -23,87 +37,155 @@ impl Parser {
         }
     }
 
+    // used when the parser runs past the end of the tokens, keeps the position of the last token for errors
+    fn eof(&self) -> Token {
+        let (line, col) = self.tokens.last().map_or((1, 1), |token| (token.line, token.col));
+        Token { value: "EOF".to_string(), r#type: TokenType::EOF, line, col }
+    }
+
+    // doc comments only mean something at the start of a statement (see parse_decorated_stmt), everywhere else at and eat skip them like regular comments
+    fn skip_doc_comments(&self) -> usize {
+        let mut pos = self.pos;
+        while self.tokens.get(pos).is_some_and(|token| token.r#type == TokenType::DocComment) {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn at_doc_comment(&self) -> bool {
+        self.tokens.get(self.pos).is_some_and(|token| token.r#type == TokenType::DocComment)
+    }
+
     fn at(&self) -> Token {
-        if let Some(current_token) = self.tokens.get(self.pos) {
+        if let Some(current_token) = self.tokens.get(self.skip_doc_comments()) {
             current_token.clone()
         } else {
-            Token { value: "EOF".to_string(), r#type: TokenType::EOF }
+            self.eof()
         }
     }
 
     // Returns the current token (before it's eaten) and then eats it
     fn eat(&mut self) -> Token {
+        self.pos = self.skip_doc_comments();
         if let Some(current_token) = self.tokens.get(self.pos) {
             self.pos += 1;
             current_token.clone()
         } else {
-            Token { value: "EOF".to_string(), r#type: TokenType::EOF }
+            self.eof()
         }
     }
 
     // Eats the token and then makes sure that it matches the expected token type, also returns the current token (before it's eaten)
-    fn expect(&mut self, r#type: TokenType, err: &'static str) -> Token {
+    fn expect(&mut self, r#type: TokenType, err: &'static str) -> Result<Token, Diagnostic> {
         let prev = self.eat();
 
         if prev.r#type == TokenType::EOF || prev.r#type != r#type {
-            panic!("Parser Error: {} - Expecting: {:#?}", err, r#type);
+            return Err(Diagnostic::error(Stage::Parser, format!("{} - Expecting: {:?}", err, r#type)).at(prev.line, prev.col));
         }
 
-        prev
+        Ok(prev)
     }
 
-    pub fn produce_ast(&mut self) -> ast::Program {
+    pub fn produce_ast(&mut self) -> Result<ast::Program, Diagnostic> {
         let mut program: ast::Program = ast::Program {
             kind: ast::NodeType::Program,
             body: vec![]
         };
 
         while self.not_eof() {
-            program.body.push(self.parse_stmt());
+            program.body.push(self.parse_stmt()?);
         }
 
-        program
+        Ok(program)
     }
 
-    fn parse_stmt(&mut self) -> ast::Stmt {
+    fn parse_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        self.nest()?;
+        let stmt = self.parse_stmt_kind();
+        self.depth -= 1;
+        stmt
+    }
+
+    fn parse_stmt_kind(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        if self.at_doc_comment() {
+            return self.parse_decorated_stmt();
+        }
+
         let tk = self.at().r#type;
 
         match tk {
             TokenType::Let => self.parse_variable_stmt(false),
             TokenType::Const => self.parse_variable_stmt(true),
+            TokenType::Type => self.parse_type_alias_stmt(),
+            TokenType::Newtype => self.parse_newtype_stmt(),
+            TokenType::Extern => self.parse_extern_fn_stmt(),
+            TokenType::At => self.parse_decorated_stmt(),
             TokenType::If => self.parse_if_stmt(),
+            TokenType::For => self.parse_for_stmt(),
+            TokenType::Yield => self.parse_yield_stmt(),
+            TokenType::Spawn => self.parse_spawn_stmt(),
+            TokenType::Defer => self.parse_defer_stmt(),
+            TokenType::Assert => self.parse_assert_stmt(),
             TokenType::OpenBrace => self.parse_block_stmt(),
-            _ => ast::Stmt::Expr(self.parse_expr())
+            _ => Ok(ast::Stmt::Expr(self.parse_expr()?))
         }
     }
 
-    fn parse_expr(&mut self) -> ast::Expr {
+    fn parse_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
         self.parse_assignment_expr()
     }
 
-    fn parse_assignment_expr(&mut self) -> ast::Expr {
-        let left = self.parse_logical_or_expr();
+    fn parse_assignment_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        self.nest()?;
+        let depth = self.depth;
+        let left = self.parse_none_fallback_expr()?;
         // TODO: add more assignment operators. *=, /=, %=
         if matches!(self.at().r#type, TokenType::Equals | TokenType::PlusEquals | TokenType::MinusEquals) {
             let operator = self.eat().value; // advance past the assignment operator to get the value of the assignment expr
-            let value = self.parse_assignment_expr();
-            return ast::Expr::AssignmentExpr(ast::AssignmentExpr {
+            let value = self.parse_assignment_expr()?;
+            self.depth = depth - 1;
+            return Ok(ast::Expr::AssignmentExpr(ast::AssignmentExpr {
                 kind: ast::NodeType::AssignmentExpr,
                 assignee: Box::new(left),
                 operator,
                 value: Box::new(value)
+            }));
+        }
+
+        self.depth = depth - 1;
+        Ok(left)
+    }
+
+    // read_line() ?? "default", binds looser than || so the fallback can be a whole condition
+    fn parse_none_fallback_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_logical_or_expr()?;
+
+        while self.at().r#type == TokenType::QuestionQuestion {
+            self.nest()?;
+            let operator = self.eat().value;
+            let right = self.parse_logical_or_expr()?;
+
+            left = ast::Expr::LogicalExpr(ast::LogicalExpr {
+                kind: ast::NodeType::LogicalExpr,
+                left: Box::new(left),
+                right: Box::new(right),
+                operator
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_logical_or_expr(&mut self) -> ast::Expr {
-        let mut left = self.parse_logical_and_expr();
+    fn parse_logical_or_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_logical_and_expr()?;
 
         while self.at().r#type == TokenType::Or {
+            self.nest()?;
             let operator = self.eat().value;
-            let right = self.parse_logical_and_expr();
+            let right = self.parse_logical_and_expr()?;
 
             left = ast::Expr::LogicalExpr(ast::LogicalExpr {
                 kind: ast::NodeType::LogicalExpr,
@@ -113,15 +195,18 @@ impl Parser {
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_logical_and_expr(&mut self) -> ast::Expr {
-        let mut left = self.parse_equality_expr();
+    fn parse_logical_and_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_equality_expr()?;
 
         while self.at().r#type == TokenType::And {
+            self.nest()?;
             let operator = self.eat().value;
-            let right = self.parse_equality_expr();
+            let right = self.parse_equality_expr()?;
 
             left = ast::Expr::LogicalExpr(ast::LogicalExpr {
                 kind: ast::NodeType::LogicalExpr,
@@ -131,15 +216,18 @@ impl Parser {
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_equality_expr(&mut self) -> ast::Expr {
-        let mut left = self.parse_comparison_expr();
+    fn parse_equality_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_comparison_expr()?;
 
         while matches!(self.at().r#type, TokenType::EqualsEquals | TokenType::NotEqual) {
+            self.nest()?;
             let operator = self.eat().value;
-            let right = self.parse_comparison_expr();
+            let right = self.parse_comparison_expr()?;
 
             left = ast::Expr::Binary(ast::BinaryExpr {
                 kind: ast::NodeType::BinaryExpr,
@@ -149,15 +237,18 @@ impl Parser {
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_comparison_expr(&mut self) -> ast::Expr {
-        let mut left = self.parse_additive_expr();
+    fn parse_comparison_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_additive_expr()?;
 
         while matches!(self.at().r#type, TokenType::LessThan | TokenType::GreaterThan | TokenType::LessThanEqual | TokenType::GreaterThanEqual) {
+            self.nest()?;
             let operator = self.eat().value;
-            let right = self.parse_additive_expr();
+            let right = self.parse_additive_expr()?;
 
             left = ast::Expr::Binary(ast::BinaryExpr {
                 kind: ast::NodeType::BinaryExpr,
@@ -167,15 +258,18 @@ impl Parser {
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_additive_expr(&mut self) -> ast::Expr {
-        let mut left = self.parse_multiplicative_expr();
+    fn parse_additive_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_multiplicative_expr()?;
 
         while matches!(self.at().r#type, TokenType::Plus | TokenType::Minus) {
+            self.nest()?;
             let operator = self.eat().value;
-            let right = self.parse_multiplicative_expr();
+            let right = self.parse_multiplicative_expr()?;
 
             left = ast::Expr::Binary(ast::BinaryExpr {
                 kind: ast::NodeType::BinaryExpr,
@@ -185,15 +279,18 @@ impl Parser {
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_multiplicative_expr(&mut self) -> ast::Expr {
-        let mut left = self.parse_unary_expr();
+    fn parse_multiplicative_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut left = self.parse_unary_expr()?;
 
         while matches!(self.at().r#type, TokenType::Star | TokenType::Slash | TokenType::Percent) {
+            self.nest()?;
             let operator = self.eat().value;
-            let right = self.parse_primary_expr();
+            let right = self.parse_call_expr()?;
 
             left = ast::Expr::Binary(ast::BinaryExpr {
                 kind: ast::NodeType::BinaryExpr,
@@ -203,33 +300,117 @@ impl Parser {
             });
         }
 
-        left
+        self.depth = depth;
+        Ok(left)
     }
 
-    fn parse_unary_expr(&mut self) -> ast::Expr {
+    fn parse_unary_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
         if matches!(self.at().r#type, TokenType::Minus | TokenType::Plus | TokenType::Not) {
             let operator = self.eat().value;
-            let value = self.parse_primary_expr();
-            return ast::Expr::UnaryExpr(ast::UnaryExpr {
+
+            // negative literals are parsed in one go, -9223372036854775808 fits in 64 bits but 9223372036854775808 doesn't
+            if operator == "-" && self.at().r#type == TokenType::Number {
+                let literal = self.eat();
+                return Ok(ast::Expr::NumericLiteral(ast::NumericLiteral {
+                    kind: ast::NodeType::NumericLiteral,
+                    value: self.parse_numeric_literal(&literal, true)?
+                }));
+            }
+
+            self.nest()?;
+            let value = self.parse_call_expr()?;
+            self.depth -= 1;
+            return Ok(ast::Expr::UnaryExpr(ast::UnaryExpr {
                 kind: ast::NodeType::UnaryExpr,
                 operator,
                 value: Box::new(value)
+            }));
+        }
+
+        self.parse_call_expr()
+    }
+
+    fn parse_call_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        let depth = self.depth;
+        let mut callee = self.parse_primary_expr()?;
+
+        // Meters(5), the callee can be called again (and again) if it is followed by more parenthesis
+        // math.sqrt(2.0), members can be accessed (and called) the same way
+        while matches!(self.at().r#type, TokenType::OpenParen | TokenType::Dot) {
+            self.nest()?;
+            if self.eat().r#type == TokenType::Dot {
+                // pair.0 is the first item of a tuple
+                let property = match self.at().r#type {
+                    TokenType::Number => self.eat().value,
+                    _ => self.expect(TokenType::Identifier, "Expected the name of a member after '.'.")?.value,
+                };
+                callee = ast::Expr::Member(ast::MemberExpr {
+                    kind: ast::NodeType::MemberExpr,
+                    object: Box::new(callee),
+                    property
+                });
+                continue;
+            }
+
+            let mut args = Vec::new();
+
+            while self.at().r#type != TokenType::CloseParen {
+                args.push(self.parse_expr()?);
+
+                if self.at().r#type != TokenType::CloseParen {
+                    self.expect(TokenType::Comma, "Expected a comma between call arguments.")?;
+                }
+            }
+
+            self.expect(TokenType::CloseParen, "Expected closing parenthesis after call arguments.")?;
+
+            callee = ast::Expr::Call(ast::CallExpr {
+                kind: ast::NodeType::CallExpr,
+                callee: Box::new(callee),
+                args
             });
         }
 
-        self.parse_primary_expr()
+        self.depth = depth;
+        Ok(callee)
     }
 
-    fn parse_primary_expr(&mut self) -> ast::Expr {
+    fn parse_primary_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
         let tk = self.at().r#type;
 
-        match tk {
+        Ok(match tk {
+            // channel<int>(), like list<T> the name is only special in front of <
+            TokenType::Identifier if self.at().value == "channel" && self.tokens.get(self.pos + 1).is_some_and(|next| next.r#type == TokenType::LessThan) => self.parse_channel_expr()?,
+
             TokenType::Identifier => ast::Expr::Identifier(ast::Identifier { kind: ast::NodeType::Identifier, symbol: self.eat().value }),
 
-            TokenType::Number => ast::Expr::NumericLiteral(ast::NumericLiteral {
-                kind: ast::NodeType::NumericLiteral,
-                value: self.eat().value.parse::<i64>().expect("Parser Error: Failed to parse numeric literal.")
-            }),
+            // type keywords used as a value convert a newtype back into what it wraps, int(distance)
+            TokenType::TypeInt | TokenType::TypeFloat | TokenType::TypeBool | TokenType::TypeStr => ast::Expr::Identifier(ast::Identifier { kind: ast::NodeType::Identifier, symbol: self.eat().value }),
+
+            TokenType::Number => {
+                let literal = self.eat();
+                ast::Expr::NumericLiteral(ast::NumericLiteral {
+                    kind: ast::NodeType::NumericLiteral,
+                    value: self.parse_numeric_literal(&literal, false)?
+                })
+            }
+
+            TokenType::Float => {
+                let literal = self.eat();
+                ast::Expr::FloatLiteral(ast::FloatLiteral {
+                    kind: ast::NodeType::FloatLiteral,
+                    value: literal.value.replace('_', "").parse().map_err(|_| {
+                        Diagnostic::error(Stage::Parser, format!("Invalid float literal '{}'.", literal.value)).at(literal.line, literal.col)
+                    })?
+                })
+            }
+
+            TokenType::String => ast::Expr::StringLiteral(ast::StringLiteral { kind: ast::NodeType::StringLiteral, value: self.eat().value }),
+
+            TokenType::None => {
+                self.eat(); // eat the none token
+                ast::Expr::NoneLiteral(ast::NoneLiteral { kind: ast::NodeType::NoneLiteral })
+            }
 
             TokenType::True => {
                 self.eat(); // eat the true token
@@ -243,73 +424,545 @@ impl Parser {
 
             TokenType::OpenParen => {
                 self.eat(); // eat the opening parenthesis
-                let value = self.parse_expr();
-                self.expect(TokenType::CloseParen, "Unexpected token found inside parenthesised expression, expected closing parenthesis.");
+                let value = self.parse_expr()?;
+
+                // (1, "a") is a tuple, (1) is just 1
+                if self.at().r#type == TokenType::Comma {
+                    self.eat(); // eat the comma
+                    let mut items = vec![value];
+                    items.extend(self.parse_items(TokenType::CloseParen, "Expected a comma between tuple items.")?);
+                    return Ok(ast::Expr::TupleLiteral(ast::TupleLiteral { kind: ast::NodeType::TupleLiteral, items }));
+                }
+
+                self.expect(TokenType::CloseParen, "Unexpected token found inside parenthesised expression, expected closing parenthesis.")?;
                 value
             }
 
+            TokenType::OpenBracket => {
+                self.eat(); // eat the opening bracket
+                let items = self.parse_items(TokenType::CloseBracket, "Expected a comma between list items.")?;
+                ast::Expr::ListLiteral(ast::ListLiteral { kind: ast::NodeType::ListLiteral, items })
+            }
+
+            TokenType::OpenBrace => self.parse_map_literal()?,
+
+            TokenType::Fn => self.parse_lambda_expr()?,
+
             _ => {
-                panic!("Parser Error: Unexpected token found during parsing: {:?}", self.at());
+                let tk = self.at();
+                return Err(Diagnostic::error(Stage::Parser, format!("Unexpected token found during parsing: {:?}", tk.r#type)).at(tk.line, tk.col));
             }
-        }
+        })
     }
 
-    fn parse_variable_stmt(&mut self, constant: bool) -> ast::Stmt {
+    // the lexer already made sure the digits are valid for the literal's base, all that's left is the range
+    fn parse_numeric_literal(&self, literal: &Token, negative: bool) -> Result<i64, Diagnostic> {
+        let (radix, digits) = match literal.value.get(..2) {
+            Some("0x" | "0X") => (16, &literal.value[2..]),
+            Some("0b" | "0B") => (2, &literal.value[2..]),
+            Some("0o" | "0O") => (8, &literal.value[2..]),
+            _ => (10, literal.value.as_str()),
+        };
+
+        let magnitude = u64::from_str_radix(&digits.replace('_', ""), radix).ok();
+        let value = match (magnitude, negative) {
+            (Some(magnitude), false) => i64::try_from(magnitude).ok(),
+            (Some(magnitude), true) => 0i64.checked_sub_unsigned(magnitude),
+            (None, _) => None,
+        };
+
+        value.ok_or_else(|| {
+            let sign = if negative { "-" } else { "" };
+            Diagnostic::error(Stage::Parser, format!("Numeric literal '{}{}' does not fit in a 64-bit integer.", sign, literal.value)).at(literal.line, literal.col)
+        })
+    }
+
+    fn parse_variable_stmt(&mut self, constant: bool) -> Result<ast::Stmt, Diagnostic> {
         self.eat(); // eat the let keyword
-        let ident = self.expect(TokenType::Identifier, "The variable you want to declare must have a proper name!");
-        self.expect(TokenType::Equals, "Expected equals sign after identifier.");
-        let value = self.parse_expr();
+        let ident = self.expect(TokenType::Identifier, "The variable you want to declare must have a proper name!")?;
+
+        // an annotation is optional, the checker infers the type from the value when it's left out
+        let mut r#type = None;
+        if self.at().r#type == TokenType::Colon {
+            self.eat(); // eat the colon
+            r#type = Some(self.parse_type()?);
+        }
 
-        ast::Stmt::VariableDeclaration(VariableDeclaration {
+        self.expect(TokenType::Equals, "Expected equals sign after identifier.")?;
+        let value = self.parse_expr()?;
+
+        Ok(ast::Stmt::VariableDeclaration(VariableDeclaration {
             kind: ast::NodeType::VariableDeclaration,
             identifier: ident.value,
             constant,
-            value
+            r#type,
+            value,
+            doc: None,
+            attributes: Vec::new()
+        }))
+    }
+
+    // int, float, bool, str, list<T>, map<K, V>, (A, B), fn(A, B): R, or the name of a type alias or newtype, followed by ? if it's optional
+    fn parse_type(&mut self) -> Result<Type, Diagnostic> {
+        self.nest()?;
+        let r#type = self.parse_type_kind();
+        self.depth -= 1;
+        r#type
+    }
+
+    fn parse_type_kind(&mut self) -> Result<Type, Diagnostic> {
+        let tk = self.eat();
+
+        let r#type = match tk.r#type {
+            // map<str, int>, like list it's only special in front of <
+            TokenType::Identifier if tk.value == "map" && self.at().r#type == TokenType::LessThan => {
+                self.eat(); // eat the <
+                let key = self.parse_type()?;
+                self.expect(TokenType::Comma, "Expected a comma between the key and value types of the map.")?;
+                let value = self.parse_type()?;
+                self.expect(TokenType::GreaterThan, "Expected '>' after the value type of the map.")?;
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            // (int, str)
+            TokenType::OpenParen => {
+                let mut items = Vec::new();
+                while self.at().r#type != TokenType::CloseParen {
+                    items.push(self.parse_type()?);
+                    if self.at().r#type != TokenType::CloseParen {
+                        self.expect(TokenType::Comma, "Expected a comma between tuple item types.")?;
+                    }
+                }
+                self.eat(); // eat the closing parenthesis
+
+                if items.len() < 2 {
+                    return Err(Diagnostic::error(Stage::Parser, "Tuple types need at least 2 items.").at(tk.line, tk.col));
+                }
+                Type::Tuple(items)
+            }
+            // fn(int, int): int, or fn(str) when nothing is returned
+            TokenType::Fn => {
+                self.expect(TokenType::OpenParen, "Expected opening parenthesis after fn in a function type.")?;
+                let mut params = Vec::new();
+                while self.at().r#type != TokenType::CloseParen {
+                    params.push(self.parse_type()?);
+                    if self.at().r#type != TokenType::CloseParen {
+                        self.expect(TokenType::Comma, "Expected a comma between parameter types.")?;
+                    }
+                }
+                self.eat(); // eat the closing parenthesis
+
+                let mut ret = Type::Void;
+                if self.at().r#type == TokenType::Colon {
+                    self.eat(); // eat the colon
+                    ret = self.parse_type()?;
+                }
+                Type::Function { params, ret: Box::new(ret) }
+            }
+            TokenType::TypeInt | TokenType::TypeFloat | TokenType::TypeBool | TokenType::TypeStr => Type::from_keyword(&tk.value).unwrap(),
+            // gen<int>, the type of what calling a generator gives back, like list it's only special in front of <
+            TokenType::Identifier if tk.value == "gen" && self.at().r#type == TokenType::LessThan => {
+                self.eat(); // eat the <
+                let inner = self.parse_type()?;
+                self.expect(TokenType::GreaterThan, "Expected '>' after the type of the generator's values.")?;
+                Type::Generator(Box::new(inner))
+            }
+            // channel<int>, the type of a channel that values of the inner type are sent through
+            TokenType::Identifier if tk.value == "channel" && self.at().r#type == TokenType::LessThan => {
+                self.eat(); // eat the <
+                let inner = self.parse_type()?;
+                self.expect(TokenType::GreaterThan, "Expected '>' after the type of the channel's values.")?;
+                Type::Channel(Box::new(inner))
+            }
+            // list<str>, list is only special in front of <, so it can still be used as a name
+            TokenType::Identifier if tk.value == "list" && self.at().r#type == TokenType::LessThan => {
+                self.eat(); // eat the <
+                let inner = self.parse_type()?;
+                self.expect(TokenType::GreaterThan, "Expected '>' after the type of the list's items.")?;
+                Type::List(Box::new(inner))
+            }
+            TokenType::Identifier => Type::Named(tk.value),
+            _ => return Err(Diagnostic::error(Stage::Parser, format!("Expected a type, got '{}'.", tk.value)).at(tk.line, tk.col)),
+        };
+
+        // str? is a str that can also be none
+        if self.at().r#type == TokenType::Question {
+            self.eat(); // eat the question mark
+            return Ok(Type::Optional(Box::new(r#type)));
+        }
+
+        Ok(r#type)
+    }
+
+    fn parse_type_alias_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // type UserId = int
+        self.eat(); // eat the type keyword
+        let ident = self.expect(TokenType::Identifier, "The type alias you want to declare must have a proper name!")?;
+        self.expect(TokenType::Equals, "Expected equals sign after the type alias name.")?;
+        let r#type = self.parse_type()?;
+
+        Ok(ast::Stmt::TypeAlias(ast::TypeAlias {
+            kind: ast::NodeType::TypeAlias,
+            identifier: ident.value,
+            r#type,
+            doc: None,
+            attributes: Vec::new()
+        }))
+    }
+
+    fn parse_newtype_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // newtype Meters(int)
+        self.eat(); // eat the newtype keyword
+        let ident = self.expect(TokenType::Identifier, "The newtype you want to declare must have a proper name!")?;
+        self.expect(TokenType::OpenParen, "Expected opening parenthesis after the newtype name.")?;
+        let inner = self.parse_type()?;
+        self.expect(TokenType::CloseParen, "Expected closing parenthesis after the type the newtype wraps.")?;
+
+        Ok(ast::Stmt::NewtypeDeclaration(ast::NewtypeDeclaration {
+            kind: ast::NodeType::NewtypeDeclaration,
+            identifier: ident.value,
+            inner,
+            doc: None,
+            attributes: Vec::new()
+        }))
+    }
+
+    // @deprecated("use y instead"), @inline
+    fn parse_attribute(&mut self) -> Result<ast::Attribute, Diagnostic> {
+        let at = self.eat(); // eat the @ symbol
+        let name = self.expect(TokenType::Identifier, "Expected the name of the attribute after '@'.")?.value;
+        let mut args = Vec::new();
+
+        if self.at().r#type == TokenType::OpenParen {
+            self.eat(); // eat the opening parenthesis
+            while self.at().r#type != TokenType::CloseParen {
+                args.push(self.parse_expr()?);
+
+                if self.at().r#type != TokenType::CloseParen {
+                    self.expect(TokenType::Comma, "Expected a comma between attribute arguments.")?;
+                }
+            }
+            self.expect(TokenType::CloseParen, "Expected closing parenthesis after attribute arguments.")?;
+        }
+
+        Ok(ast::Attribute {
+            kind: ast::NodeType::Attribute,
+            name,
+            args,
+            line: at.line,
+            col: at.col
         })
     }
 
-    fn parse_block_stmt(&mut self) -> ast::Stmt {
+    // ## doc comments and @attributes belong to the declaration right below them
+    // doc comments anywhere else are treated like regular comments, attributes anywhere else are an error
+    fn parse_decorated_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        let mut lines = Vec::new();
+        let mut attributes = Vec::new();
+        loop {
+            if self.at_doc_comment() {
+                lines.push(self.tokens[self.pos].value.clone());
+                self.pos += 1;
+            } else if self.at().r#type == TokenType::At {
+                attributes.push(self.parse_attribute()?);
+            } else {
+                break;
+            }
+        }
+
+        let doc = if lines.is_empty() { None } else { Some(lines.join("\n")) };
+        let mut stmt = if self.not_eof() && self.at().r#type != TokenType::CloseBrace {
+            self.parse_stmt()?
+        } else if attributes.is_empty() {
+            // nothing left to document, give back an empty block so the caller still gets a statement
+            return Ok(ast::Stmt::BlockStatement(ast::BlockStatement { kind: ast::NodeType::BlockStatement, body: Vec::new() }));
+        } else {
+            return Err(Diagnostic::error(Stage::Parser, format!("Attribute '@{}' is not followed by a declaration.", attributes[0].name)).at(attributes[0].line, attributes[0].col));
+        };
+
+        match &mut stmt {
+            ast::Stmt::VariableDeclaration(vardecl) => {
+                vardecl.doc = doc;
+                vardecl.attributes = attributes;
+            }
+            ast::Stmt::TypeAlias(type_alias) => {
+                type_alias.doc = doc;
+                type_alias.attributes = attributes;
+            }
+            ast::Stmt::NewtypeDeclaration(newtype) => {
+                newtype.doc = doc;
+                newtype.attributes = attributes;
+            }
+            ast::Stmt::ExternFunctionDeclaration(extern_fn) => {
+                extern_fn.doc = doc;
+                extern_fn.attributes = attributes;
+            }
+            _ if !attributes.is_empty() => {
+                return Err(Diagnostic::error(Stage::Parser, "Attributes can only be placed on declarations.").at(attributes[0].line, attributes[0].col));
+            }
+            _ => { /* not a declaration, the doc comment is dropped */ }
+        }
+
+        Ok(stmt)
+    }
+
+    fn parse_extern_fn_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // extern fn now(): int or extern fn log(level: int, message: str)
+        self.eat(); // eat the extern keyword
+        self.expect(TokenType::Fn, "Expected the fn keyword after extern.")?;
+        let ident = self.expect(TokenType::Identifier, "The extern function you want to declare must have a proper name!")?;
+        self.expect(TokenType::OpenParen, "Expected opening parenthesis after the function name.")?;
+        let params = self.parse_params()?;
+
+        let mut return_type = Type::Void;
+        if self.at().r#type == TokenType::Colon {
+            self.eat(); // eat the colon
+            return_type = self.parse_type()?;
+        }
+
+        Ok(ast::Stmt::ExternFunctionDeclaration(ast::ExternFunctionDeclaration {
+            kind: ast::NodeType::ExternFunctionDeclaration,
+            identifier: ident.value,
+            params,
+            return_type,
+            doc: None,
+            attributes: Vec::new()
+        }))
+    }
+
+    // (name: type, name: type), after the opening parenthesis, shared by extern functions and lambdas
+    fn parse_params(&mut self) -> Result<Vec<ast::Parameter>, Diagnostic> {
+        let mut params = Vec::new();
+        while self.at().r#type != TokenType::CloseParen {
+            let name = self.expect(TokenType::Identifier, "Expected the name of a parameter.")?.value;
+            self.expect(TokenType::Colon, "Expected a colon and a type after the parameter name, parameters must have a type.")?;
+            params.push(ast::Parameter { name, r#type: self.parse_type()? });
+
+            if self.at().r#type != TokenType::CloseParen {
+                self.expect(TokenType::Comma, "Expected a comma between parameters.")?;
+            }
+        }
+        self.expect(TokenType::CloseParen, "Expected closing parenthesis after the parameters.")?;
+
+        Ok(params)
+    }
+
+    // fn(x: int): int => x * 2, the return type can be left out
+    fn parse_lambda_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        self.eat(); // eat the fn keyword
+        self.expect(TokenType::OpenParen, "Expected opening parenthesis after fn, lambdas don't have a name.")?;
+        let params = self.parse_params()?;
+
+        let mut return_type = None;
+        if self.at().r#type == TokenType::Colon {
+            self.eat(); // eat the colon
+            return_type = Some(self.parse_type()?);
+        }
+
+        // a generator's body is a block, fn(n: int): gen<int> { yield n }, and so is the body of a lambda that gives back nothing
+        let body = if self.at().r#type == TokenType::OpenBrace {
+            ast::LambdaBody::Block(self.parse_block()?)
+        } else {
+            self.expect(TokenType::FatArrow, "Expected '=>' before the body of the lambda.")?;
+            ast::LambdaBody::Expr(Box::new(self.parse_expr()?))
+        };
+
+        Ok(ast::Expr::Lambda(ast::Lambda {
+            kind: ast::NodeType::Lambda,
+            params,
+            return_type,
+            body
+        }))
+    }
+
+    // channel<int>(), the channel's type has to be written out since nothing has been sent through it yet
+    fn parse_channel_expr(&mut self) -> Result<ast::Expr, Diagnostic> {
+        self.eat(); // eat the channel name
+        self.eat(); // eat the <
+        let r#type = self.parse_type()?;
+        self.expect(TokenType::GreaterThan, "Expected '>' after the type of the channel's values.")?;
+        self.expect(TokenType::OpenParen, "Expected opening parenthesis after the channel's type, channel<int>() makes a channel.")?;
+        self.expect(TokenType::CloseParen, "Expected closing parenthesis, making a channel doesn't take any arguments.")?;
+
+        Ok(ast::Expr::ChannelExpr(ast::ChannelExpr { kind: ast::NodeType::ChannelExpr, r#type }))
+    }
+
+    // the items of a list or tuple literal up to the closing token, a trailing comma is allowed
+    fn parse_items(&mut self, close: TokenType, err: &'static str) -> Result<Vec<ast::Expr>, Diagnostic> {
+        let mut items = Vec::new();
+        while self.at().r#type != close {
+            items.push(self.parse_expr()?);
+
+            if self.at().r#type != close {
+                self.expect(TokenType::Comma, err)?;
+            }
+        }
+        self.eat(); // eat the closing token
+
+        Ok(items)
+    }
+
+    // {"a": 1, "b": 2}, only in places where an expression is expected, a { at the start of a statement is a block
+    fn parse_map_literal(&mut self) -> Result<ast::Expr, Diagnostic> {
+        self.eat(); // eat the open brace
+        let mut entries = Vec::new();
+
+        while self.at().r#type != TokenType::CloseBrace {
+            let key = self.parse_expr()?;
+            self.expect(TokenType::Colon, "Expected a colon between the key and the value of a map entry.")?;
+            entries.push((key, self.parse_expr()?));
+
+            if self.at().r#type != TokenType::CloseBrace {
+                self.expect(TokenType::Comma, "Expected a comma between map entries.")?;
+            }
+        }
+        self.eat(); // eat the close brace
+
+        Ok(ast::Expr::MapLiteral(ast::MapLiteral { kind: ast::NodeType::MapLiteral, entries }))
+    }
+
+    fn parse_block_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        Ok(ast::Stmt::BlockStatement(self.parse_block()?))
+    }
+
+    fn parse_block(&mut self) -> Result<ast::BlockStatement, Diagnostic> {
         // { body }
         self.eat(); // eat the open brace
         let mut body = Vec::new();
         while self.at().r#type != TokenType::CloseBrace {
             if !self.not_eof() {
-                eprintln!("Parser Error: Expected closing brace '}}' for block statement, got EOF.");
-                process::exit(1);
+                let eof = self.at();
+                return Err(Diagnostic::error(Stage::Parser, "Expected closing brace '}' for block statement, got EOF.").at(eof.line, eof.col));
             }
-            body.push(self.parse_stmt());
+            body.push(self.parse_stmt()?);
         }
 
         self.eat(); // eat the closing brace, we dont have to use expect because the closing brace expectation is handled in the loop
 
-        ast::Stmt::BlockStatement(ast::BlockStatement {
+        Ok(ast::BlockStatement {
             kind: ast::NodeType::BlockStatement,
             body
         })
     }
 
-    fn parse_if_stmt(&mut self) -> ast::Stmt {
+    fn parse_assert_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // assert test or assert test, "message"
+        let line = self.eat().line; // eat the assert keyword
+        let start = self.pos;
+        let test = self.parse_expr()?;
+        let source = self.source_text(start, self.pos);
+
+        let mut message = None;
+        if self.at().r#type == TokenType::Comma {
+            self.eat(); // eat the comma
+            message = Some(self.parse_expr()?);
+        }
+
+        Ok(ast::Stmt::AssertStatement(ast::AssertStatement {
+            kind: ast::NodeType::AssertStatement,
+            test,
+            message,
+            source,
+            line
+        }))
+    }
+
+    // rebuilds the source code of the tokens from start to end (exclusive), tokens that were seperated by whitespace get a single space between them
+    fn source_text(&self, start: usize, end: usize) -> String {
+        let mut text = String::new();
+        let mut prev_end = None;
+
+        for token in self.tokens[start..end].iter().filter(|token| token.r#type != TokenType::DocComment) {
+            let token_text = match token.r#type {
+                TokenType::String => format!("{:?}", token.value),
+                _ => token.value.clone(),
+            };
+
+            if let Some((line, col)) = prev_end && (token.line, token.col) != (line, col) {
+                text.push(' ');
+            }
+
+            prev_end = Some((token.line, token.col + token_text.chars().count()));
+            text.push_str(&token_text);
+        }
+
+        text
+    }
+
+    fn parse_for_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // for x in xs { body } or for x in xs stmt
+        self.eat(); // eat the for keyword
+        let variable = self.expect(TokenType::Identifier, "Expected the name of the loop variable after for.")?.value;
+        self.expect(TokenType::In, "Expected in after the loop variable.")?;
+        let iterable = self.parse_expr()?;
+        let body = Box::new(self.parse_stmt()?);
+
+        Ok(ast::Stmt::ForStatement(ast::ForStatement {
+            kind: ast::NodeType::ForStatement,
+            variable,
+            iterable,
+            body,
+        }))
+    }
+
+    fn parse_yield_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // yield value
+        self.eat(); // eat the yield keyword
+        let value = self.parse_expr()?;
+
+        Ok(ast::Stmt::YieldStatement(ast::YieldStatement {
+            kind: ast::NodeType::YieldStatement,
+            value,
+        }))
+    }
+
+    fn parse_spawn_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // spawn fn() { body } or spawn worker(a, b), a call is taken apart so the task is the one that makes it
+        self.eat(); // eat the spawn keyword
+        let (function, args) = match self.parse_expr()? {
+            ast::Expr::Call(call_expr) => (*call_expr.callee, call_expr.args),
+            function => (function, Vec::new()),
+        };
+
+        Ok(ast::Stmt::SpawnStatement(ast::SpawnStatement {
+            kind: ast::NodeType::SpawnStatement,
+            function,
+            args,
+        }))
+    }
+
+    fn parse_defer_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
+        // defer value
+        self.eat(); // eat the defer keyword
+        let value = self.parse_expr()?;
+
+        Ok(ast::Stmt::DeferStatement(ast::DeferStatement {
+            kind: ast::NodeType::DeferStatement,
+            value,
+        }))
+    }
+
+    fn parse_if_stmt(&mut self) -> Result<ast::Stmt, Diagnostic> {
         // if awer { body } or if awer stmt
         self.eat(); // eat the if keyword
-        let test = self.parse_expr(); // the test
-        let body = Box::new(self.parse_stmt()); // can be a regular stmt or a block statement
+        let test = self.parse_expr()?; // the test
+        let body = Box::new(self.parse_stmt()?); // can be a regular stmt or a block statement
         let mut alternate = None;
 
         // check for alternate condition below
         if self.at().r#type == TokenType::Else {
             self.eat(); // eat the else keyword
             if self.at().r#type == TokenType::If {
-                alternate = Some(Box::new(self.parse_if_stmt())); // restart recursively with more conditions
+                alternate = Some(Box::new(self.parse_if_stmt()?)); // restart recursively with more conditions
             } else {
-                alternate = Some(Box::new(self.parse_stmt())); // just give the non conditional statement for else
+                alternate = Some(Box::new(self.parse_stmt()?)); // just give the non conditional statement for else
             }
         }
 
-        ast::Stmt::IfStatement(ast::IfStatement {
+        Ok(ast::Stmt::IfStatement(ast::IfStatement {
             kind: ast::NodeType::IfStatement,
             test,
             alternate,
             body,
-        })
+        }))
     }
 }
@@ -0,0 +1,147 @@
+// fs.read_text("notes.txt"), fs.write_text("out.txt", text), fs.exists(path), fs.list_dir(".")
+// programs can't touch the file system unless the host allows it, per directory, with VM::allow_read and VM::allow_write (cobalt run --allow-read=DIR)
+// paths are resolved the same way the OS would (following symlinks and ..) before they are checked, so a program can't get out of an allowed directory
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use super::{native_name, take, Member};
+use crate::errors::{Access, VmError};
+use crate::interpreter::value::mismatch;
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let (params, ret) = match name {
+        "read_text" => (vec![Type::Str], Type::Str),
+        "write_text" => (vec![Type::Str, Type::Str], Type::Void),
+        "exists" => (vec![Type::Str], Type::Bool),
+        "list_dir" => (vec![Type::Str], Type::List(Box::new(Type::Str))),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+/// The directories a program may read from and write to, everything inside them is allowed too. Nothing is allowed by default.
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+}
+
+impl Permissions {
+    /// Allows reading anything inside `dir`, which has to exist.
+    pub fn allow_read(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.read.push(dir.as_ref().canonicalize()?);
+        Ok(())
+    }
+
+    /// Allows writing anything inside `dir`, which has to exist.
+    pub fn allow_write(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.write.push(dir.as_ref().canonicalize()?);
+        Ok(())
+    }
+
+    fn allows(&self, access: Access, path: &Path) -> bool {
+        let allowed = match access {
+            Access::Read => &self.read,
+            Access::Write => &self.write,
+            Access::Env => return false,
+        };
+        allowed.iter().any(|dir| path.starts_with(dir))
+    }
+}
+
+// how many symlinks to things that don't exist yet are followed before giving up, the same limit Linux has
+const MAX_SYMLINKS: usize = 40;
+
+// the absolute path with symlinks and .. resolved, one component at a time like the OS does it
+fn resolve(path: &str) -> io::Result<PathBuf> {
+    resolve_from(&std::env::current_dir()?.join(path), 0)
+}
+
+fn resolve_from(path: &Path, links: usize) -> io::Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                if resolved.exists() {
+                    resolved = resolved.canonicalize()?;
+                } else if resolved.symlink_metadata().is_ok() {
+                    // a symlink to something that doesn't exist yet, writing to it creates its target, so the target is what gets checked
+                    if links == MAX_SYMLINKS {
+                        return Err(io::Error::other("too many levels of symbolic links"));
+                    }
+                    let target = fs::read_link(&resolved)?;
+                    resolved.pop();
+                    resolved = resolve_from(&resolved.join(target), links + 1)?;
+                }
+                // anything else doesn't exist yet (a file about to be written), so it can't lead anywhere else
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+// the path argument, resolved and checked against the permissions
+fn path(vm: &VM, access: Access, value: &Value) -> Result<PathBuf, VmError> {
+    let Value::Str(path) = value else {
+        return Err(vm.error(mismatch("str", value)));
+    };
+
+    let denied = || VmError::PermissionDenied { access, path: path.clone(), ip: vm.ip() };
+    let resolved = resolve(path).map_err(|_| denied())?;
+    if !vm.permissions().allows(access, &resolved) {
+        return Err(denied());
+    }
+
+    Ok(resolved)
+}
+
+fn failed(vm: &VM, name: &str, path: &Path, err: io::Error) -> VmError {
+    vm.error(format!("fs.{} failed for '{}': {}", name, path.display(), err))
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("fs", member);
+
+    vm.register_vm_native(&name("read_text"), 1, |vm, args| {
+        let [file] = take(args);
+        let file = path(vm, Access::Read, &file)?;
+        fs::read_to_string(&file).map(Value::Str).map_err(|err| failed(vm, "read_text", &file, err))
+    });
+    vm.register_vm_native(&name("write_text"), 2, |vm, args| {
+        let [file, text] = take(args);
+        let file = path(vm, Access::Write, &file)?;
+        let Value::Str(text) = text else {
+            return Err(vm.error(mismatch("str", &text)));
+        };
+        fs::write(&file, text).map(|_| Value::None).map_err(|err| failed(vm, "write_text", &file, err))
+    });
+    vm.register_vm_native(&name("exists"), 1, |vm, args| {
+        let [file] = take(args);
+        Ok(Value::Bool(path(vm, Access::Read, &file)?.exists()))
+    });
+    vm.register_vm_native(&name("list_dir"), 1, |vm, args| {
+        // sorted, the order the OS gives them in can change from run to run
+        let [dir] = take(args);
+        let dir = path(vm, Access::Read, &dir)?;
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(|err| failed(vm, "list_dir", &dir, err))? {
+            let entry = entry.map_err(|err| failed(vm, "list_dir", &dir, err))?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(Value::from(names))
+    });
+}
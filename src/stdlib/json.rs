@@ -0,0 +1,400 @@
+// json.parse(text), json.stringify(value, true), json.get(config, "port"), json.as_int(port)
+// parsed JSON has the json type, at runtime it's the plain value (objects are maps with str keys, arrays are lists, null is none)
+// the accessors give none when the value isn't what was asked for, so a missing key and a key set to null look the same
+
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+
+use super::{bool, int, native_name, str, Member};
+use crate::interpreter::vm::{Key, Value, VM};
+use crate::typechecker::types::Type;
+
+// deeper nesting is an error instead of running out of stack
+const MAX_DEPTH: usize = 256;
+
+pub fn member(name: &str) -> Option<Member> {
+    let optional = |inner: Type| Type::Optional(Box::new(inner));
+
+    let (params, ret) = match name {
+        "parse" => (vec![Type::Str], Type::Json),
+        "stringify" => (vec![Type::Param("T"), Type::Bool], Type::Str),
+        "get" => (vec![Type::Json, Type::Str], optional(Type::Json)),
+        "at" => (vec![Type::Json, Type::Int], optional(Type::Json)),
+        "is_null" => (vec![Type::Json], Type::Bool),
+        "as_int" => (vec![Type::Json], optional(Type::Int)),
+        "as_float" => (vec![Type::Json], optional(Type::Float)),
+        "as_str" => (vec![Type::Json], optional(Type::Str)),
+        "as_bool" => (vec![Type::Json], optional(Type::Bool)),
+        "as_list" => (vec![Type::Json], optional(Type::List(Box::new(Type::Json)))),
+        "as_map" => (vec![Type::Json], optional(Type::Map(Box::new(Type::Str), Box::new(Type::Json)))),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize, // in bytes
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: impl Into<String>) -> String {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let col = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        format!("Invalid JSON at line {}, column {}: {}", line, col, msg.into())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected '{}', found '{}'.", expected, c))),
+            None => Err(self.error(format!("expected '{}', found the end of the text.", expected))),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::parse_object),
+            Some('[') => self.nested(Self::parse_array),
+            Some('"') => self.parse_string().map(Value::Str),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('a'..='z') => self.parse_literal(),
+            Some(c) => Err(self.error(format!("expected a value, found '{}'.", c))),
+            None => Err(self.error("expected a value, found the end of the text.")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value, String>) -> Result<Value, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!("objects and arrays can only be nested {} deep.", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<Value, String> {
+        self.pos += 1; // eat the {
+        let mut entries = IndexMap::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Map(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string as the key."));
+            }
+            let key = Key::Str(self.parse_string()?);
+            self.expect(':')?;
+            let value = self.parse_value()?;
+
+            // a key that's given twice keeps the last value, but stays where it was first given
+            entries.insert(key, value);
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Map(entries)),
+                _ => return Err(self.error("expected ',' or '}' after the value.")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, String> {
+        self.pos += 1; // eat the [
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::List(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::List(items)),
+                _ => return Err(self.error("expected ',' or ']' after the item.")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.pos += 1; // eat the opening quote
+        let mut string = String::new();
+
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.parse_unicode_escape()?,
+                        _ => return Err(self.error("invalid escape in string.")),
+                    };
+                    string.push(escaped);
+                }
+                Some(c) if c < ' ' => return Err(self.error("control characters in strings have to be escaped.")),
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string.")),
+            }
+        }
+    }
+
+    // \u1234, characters outside of the basic plane are written as two of them (a surrogate pair)
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.parse_hex()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape."));
+        }
+
+        if !self.text[self.pos..].starts_with("\\u") {
+            return Err(self.error("expected the second half of a surrogate pair."));
+        }
+        self.pos += 2;
+        let low = self.parse_hex()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(self.error("invalid second half of a surrogate pair."));
+        }
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).ok_or_else(|| self.error("invalid unicode escape."))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.pos..self.pos + 4).filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()));
+        let Some(digits) = digits else {
+            return Err(self.error("expected 4 hexadecimal digits after \\u."));
+        };
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    // whole numbers that fit become ints, everything else becomes a float
+    fn parse_number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let from = parser.pos;
+            while let Some('0'..='9') = parser.peek() {
+                parser.pos += 1;
+            }
+            parser.pos > from
+        };
+
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        let leading_zero = self.peek() == Some('0');
+        let integer_start = self.pos;
+        if !digits(self) {
+            return Err(self.error("expected a digit."));
+        }
+        if leading_zero && self.pos - integer_start > 1 {
+            return Err(self.error("numbers can't start with 0."));
+        }
+
+        let mut whole = true;
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            whole = false;
+            if !digits(self) {
+                return Err(self.error("expected a digit after the decimal point."));
+            }
+        }
+        if let Some('e' | 'E') = self.peek() {
+            self.pos += 1;
+            whole = false;
+            if let Some('+' | '-') = self.peek() {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected a digit in the exponent."));
+            }
+        }
+
+        let number = &self.text[start..self.pos];
+        if whole && let Ok(value) = number.parse() {
+            return Ok(Value::Int(value));
+        }
+        Ok(Value::Float(number.parse().unwrap()))
+    }
+
+    fn parse_literal(&mut self) -> Result<Value, String> {
+        let rest = &self.text[self.pos..];
+        for (word, value) in [("true", Value::Bool(true)), ("false", Value::Bool(false)), ("null", Value::None)] {
+            if rest.starts_with(word) {
+                self.pos += word.len();
+                return Ok(value);
+            }
+        }
+        Err(self.error("expected a value."))
+    }
+}
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { text, pos: 0, depth: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("expected the end of the text after the value."));
+    }
+    Ok(value)
+}
+
+/// Turns a value into JSON text. Tuples become arrays, map keys become strings, none becomes null.
+///
+/// `pretty`: Puts every item on its own line, indented by 2 spaces
+pub fn stringify(value: &Value, pretty: bool) -> Result<String, String> {
+    let mut out = String::new();
+    write_value(&mut out, value, pretty, 0)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut String, value: &Value, pretty: bool, depth: usize) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("Values can only be nested {} deep to be turned into JSON.", MAX_DEPTH));
+    }
+
+    match value {
+        Value::Int(value) => write!(out, "{}", value).unwrap(),
+        Value::Float(value) if value.is_finite() => write!(out, "{:?}", value).unwrap(),
+        Value::Float(value) => return Err(format!("{} can't be turned into JSON.", value)),
+        Value::Bool(value) => write!(out, "{}", value).unwrap(),
+        Value::Str(value) => write_string(out, value),
+        Value::List(items) | Value::Tuple(items) => {
+            write_container(out, ('[', ']'), items.iter(), pretty, depth, |out, item| write_value(out, item, pretty, depth + 1))?;
+        }
+        Value::Map(entries) => {
+            write_container(out, ('{', '}'), entries.iter(), pretty, depth, |out, (key, value)| {
+                match key {
+                    Key::Str(key) => write_string(out, key),
+                    key => write_string(out, &Value::from(key.clone()).to_string()),
+                }
+                out.push_str(if pretty { ": " } else { ":" });
+                write_value(out, value, pretty, depth + 1)
+            })?;
+        }
+        Value::Function { .. } => return Err("Functions can't be turned into JSON.".to_string()),
+        Value::Generator(_) => return Err("Generators can't be turned into JSON.".to_string()),
+        Value::Channel(_) => return Err("Channels can't be turned into JSON.".to_string()),
+        Value::None => out.push_str("null"),
+    }
+
+    Ok(())
+}
+
+fn write_container<T>(
+    out: &mut String,
+    (open, close): (char, char),
+    items: impl ExactSizeIterator<Item = T>,
+    pretty: bool,
+    depth: usize,
+    mut write_item: impl FnMut(&mut String, T) -> Result<(), String>,
+) -> Result<(), String> {
+    let empty = items.len() == 0;
+    out.push(open);
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if pretty {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth + 1));
+        }
+        write_item(out, item)?;
+    }
+    if pretty && !empty {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+    }
+    out.push(close);
+    Ok(())
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("json", member);
+
+    vm.register_native(&name("parse"), 1, |args| parse(str(args, 0)?));
+    vm.register_native(&name("stringify"), 2, |args| stringify(&args[0], bool(args, 1)?).map(Value::Str));
+    vm.register_native(&name("get"), 2, |args| {
+        let key = str(args, 1)?;
+        Ok(match &args[0] {
+            Value::Map(entries) => entries.get(&Key::Str(key.to_string())).cloned().into(),
+            _ => Value::None,
+        })
+    });
+    vm.register_native(&name("at"), 2, |args| {
+        let index = int(args, 1)?;
+        Ok(match &args[0] {
+            Value::List(items) => usize::try_from(index).ok().and_then(|index| items.get(index)).cloned().unwrap_or(Value::None),
+            _ => Value::None,
+        })
+    });
+    vm.register_native(&name("is_null"), 1, |args| Ok(Value::Bool(matches!(args[0], Value::None))));
+    vm.register_native(&name("as_int"), 1, |args| Ok(if let Value::Int(_) = args[0] { args[0].clone() } else { Value::None }));
+    vm.register_native(&name("as_float"), 1, |args| {
+        // whole numbers are parsed as ints, so they are floats too
+        Ok(match args[0] {
+            Value::Float(_) => args[0].clone(),
+            Value::Int(value) => Value::Float(value as f64),
+            _ => Value::None,
+        })
+    });
+    vm.register_native(&name("as_str"), 1, |args| Ok(if let Value::Str(_) = args[0] { args[0].clone() } else { Value::None }));
+    vm.register_native(&name("as_bool"), 1, |args| Ok(if let Value::Bool(_) = args[0] { args[0].clone() } else { Value::None }));
+    vm.register_native(&name("as_list"), 1, |args| Ok(if let Value::List(_) = args[0] { args[0].clone() } else { Value::None }));
+    vm.register_native(&name("as_map"), 1, |args| Ok(if let Value::Map(_) = args[0] { args[0].clone() } else { Value::None }));
+}
@@ -0,0 +1,156 @@
+// list.map(xs, fn(x: int): int => x * 2), list.sort(xs), list.zip(xs, ys)
+// lists are values, so every function gives back a new list and leaves the one it was given alone
+// the functions that take a lambda are VM natives, they run the lambda with VM::call_value for every item
+
+use std::cmp::Ordering;
+
+use super::{native_name, take, Member};
+use crate::errors::VmError;
+use crate::interpreter::value::mismatch;
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let (t, u) = (Type::Param("T"), Type::Param("U"));
+    let list = |item: &Type| Type::List(Box::new(item.clone()));
+    let function = |params: Vec<Type>, ret: Type| Type::Function { params, ret: Box::new(ret) };
+
+    let (params, ret) = match name {
+        "len" => (vec![list(&t)], Type::Int),
+        "get" => (vec![list(&t), Type::Int], Type::Optional(Box::new(t))),
+        "contains" => (vec![list(&t), t.clone()], Type::Bool),
+        "reverse" => (vec![list(&t)], list(&t)),
+        "sort" => (vec![list(&t)], list(&t)),
+        "sort_by" => (vec![list(&t), function(vec![t.clone()], u)], list(&t)),
+        "map" => (vec![list(&t), function(vec![t.clone()], u.clone())], list(&u)),
+        "filter" => (vec![list(&t), function(vec![t.clone()], Type::Bool)], list(&t)),
+        "reduce" => (vec![list(&t), u.clone(), function(vec![u.clone(), t.clone()], u.clone())], u),
+        "enumerate" => (vec![list(&t)], list(&Type::Tuple(vec![Type::Int, t]))),
+        "zip" => (vec![list(&t), list(&u)], list(&Type::Tuple(vec![t, u]))),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+fn take_list(vm: &VM, value: Value) -> Result<Vec<Value>, VmError> {
+    match value {
+        Value::List(items) => Ok(items),
+        other => Err(vm.error(mismatch("list", &other))),
+    }
+}
+
+// ints, floats, strs, bools and tuples of them can be sorted, floats only when none of them are NaN
+// every item of a list has the same type, so once they are all sortable comparing them is a total order
+fn sortable(value: &Value) -> bool {
+    match value {
+        Value::Int(_) | Value::Str(_) | Value::Bool(_) => true,
+        Value::Float(value) => !value.is_nan(),
+        Value::Tuple(items) => items.iter().all(sortable),
+        _ => false,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Ordering {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => l.cmp(r),
+        (Value::Float(l), Value::Float(r)) => l.partial_cmp(r).unwrap_or(Ordering::Equal),
+        (Value::Str(l), Value::Str(r)) => l.cmp(r),
+        (Value::Bool(l), Value::Bool(r)) => l.cmp(r),
+        (Value::Tuple(l), Value::Tuple(r)) => l.iter().zip(r).map(|(l, r)| compare(l, r)).find(|order| order.is_ne()).unwrap_or(Ordering::Equal),
+        _ => Ordering::Equal,
+    }
+}
+
+fn check_sortable(vm: &VM, name: &str, values: &[Value]) -> Result<(), VmError> {
+    match values.iter().find(|value| !sortable(value)) {
+        Some(value) => Err(vm.error(format!("list.{} can only sort ints, floats (that aren't NaN), strs, bools and tuples of them, but found '{}'.", name, value))),
+        None => Ok(()),
+    }
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("list", member);
+
+    vm.register_vm_native(&name("len"), 1, |vm, args| {
+        let [list] = take(args);
+        Ok(Value::Int(take_list(vm, list)?.len() as i64))
+    });
+    vm.register_vm_native(&name("get"), 2, |vm, args| {
+        let [list, index] = take(args);
+        let mut items = take_list(vm, list)?;
+        let index = i64::try_from(index).map_err(|msg| vm.error(msg))?;
+
+        // out of range (or negative) gives none instead of an error
+        Ok(usize::try_from(index).ok().filter(|index| *index < items.len()).map_or(Value::None, |index| items.swap_remove(index)))
+    });
+    vm.register_vm_native(&name("contains"), 2, |vm, args| {
+        let [list, value] = take(args);
+        Ok(Value::Bool(take_list(vm, list)?.contains(&value)))
+    });
+    vm.register_vm_native(&name("reverse"), 1, |vm, args| {
+        let [list] = take(args);
+        let mut items = take_list(vm, list)?;
+        items.reverse();
+        Ok(Value::List(items))
+    });
+    vm.register_vm_native(&name("sort"), 1, |vm, args| {
+        let [list] = take(args);
+        let mut items = take_list(vm, list)?;
+        check_sortable(vm, "sort", &items)?;
+        items.sort_by(compare);
+        Ok(Value::List(items))
+    });
+    vm.register_vm_native(&name("sort_by"), 2, |vm, args| {
+        // the key of every item is worked out once, then the items are sorted by their keys (items with equal keys keep their order)
+        let [list, key] = take(args);
+        let mut keyed = Vec::new();
+        for item in take_list(vm, list)? {
+            keyed.push((vm.call_value(&key, vec![item.clone()])?, item));
+        }
+
+        let keys = keyed.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+        check_sortable(vm, "sort_by", &keys)?;
+        keyed.sort_by(|(l, _), (r, _)| compare(l, r));
+        Ok(Value::List(keyed.into_iter().map(|(_, item)| item).collect()))
+    });
+    vm.register_vm_native(&name("map"), 2, |vm, args| {
+        let [list, function] = take(args);
+        let mut mapped = Vec::new();
+        for item in take_list(vm, list)? {
+            mapped.push(vm.call_value(&function, vec![item])?);
+        }
+        Ok(Value::List(mapped))
+    });
+    vm.register_vm_native(&name("filter"), 2, |vm, args| {
+        let [list, predicate] = take(args);
+        let mut kept = Vec::new();
+        for item in take_list(vm, list)? {
+            match vm.call_value(&predicate, vec![item.clone()])? {
+                Value::Bool(true) => kept.push(item),
+                Value::Bool(false) => {}
+                other => return Err(vm.error(mismatch("bool", &other))),
+            }
+        }
+        Ok(Value::List(kept))
+    });
+    vm.register_vm_native(&name("reduce"), 3, |vm, args| {
+        let [list, initial, function] = take(args);
+        let mut accumulator = initial;
+        for item in take_list(vm, list)? {
+            accumulator = vm.call_value(&function, vec![accumulator, item])?;
+        }
+        Ok(accumulator)
+    });
+    vm.register_vm_native(&name("enumerate"), 1, |vm, args| {
+        let [list] = take(args);
+        let items = take_list(vm, list)?.into_iter().enumerate();
+        Ok(Value::List(items.map(|(index, item)| Value::Tuple(vec![Value::Int(index as i64), item])).collect()))
+    });
+    vm.register_vm_native(&name("zip"), 2, |vm, args| {
+        // stops at the end of the shorter list
+        let [left, right] = take(args);
+        let pairs = take_list(vm, left)?.into_iter().zip(take_list(vm, right)?);
+        Ok(Value::List(pairs.map(|(l, r)| Value::Tuple(vec![l, r])).collect()))
+    });
+}
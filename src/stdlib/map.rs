@@ -0,0 +1,64 @@
+// map.keys(ages), map.get(ages, "ann") ?? 0
+// maps keep the order their keys were first added in, so keys and values come out in that order
+
+use indexmap::IndexMap;
+
+use super::{native_name, take, Member};
+use crate::errors::VmError;
+use crate::interpreter::value::mismatch;
+use crate::interpreter::vm::{Key, Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let (k, v) = (Type::Param("K"), Type::Param("V"));
+    let map = Type::Map(Box::new(k.clone()), Box::new(v.clone()));
+
+    let (params, ret) = match name {
+        "len" => (vec![map], Type::Int),
+        "get" => (vec![map, k], Type::Optional(Box::new(v))),
+        "contains" => (vec![map, k], Type::Bool),
+        "keys" => (vec![map], Type::List(Box::new(k))),
+        "values" => (vec![map], Type::List(Box::new(v))),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+fn take_map(vm: &VM, value: Value) -> Result<IndexMap<Key, Value>, VmError> {
+    match value {
+        Value::Map(entries) => Ok(entries),
+        other => Err(vm.error(mismatch("map", &other))),
+    }
+}
+
+fn take_key(vm: &VM, value: Value) -> Result<Key, VmError> {
+    Key::try_from(value).map_err(|message| vm.error(message))
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("map", member);
+
+    vm.register_vm_native(&name("len"), 1, |vm, args| {
+        let [map] = take(args);
+        Ok(Value::Int(take_map(vm, map)?.len() as i64))
+    });
+    vm.register_vm_native(&name("get"), 2, |vm, args| {
+        let [map, key] = take(args);
+        let key = take_key(vm, key)?;
+        Ok(take_map(vm, map)?.swap_remove(&key).into())
+    });
+    vm.register_vm_native(&name("contains"), 2, |vm, args| {
+        let [map, key] = take(args);
+        let key = take_key(vm, key)?;
+        Ok(Value::Bool(take_map(vm, map)?.contains_key(&key)))
+    });
+    vm.register_vm_native(&name("keys"), 1, |vm, args| {
+        let [map] = take(args);
+        Ok(Value::List(take_map(vm, map)?.into_keys().map(Value::from).collect()))
+    });
+    vm.register_vm_native(&name("values"), 1, |vm, args| {
+        let [map] = take(args);
+        Ok(Value::List(take_map(vm, map)?.into_values().collect()))
+    });
+}
@@ -0,0 +1,89 @@
+// math.abs(-5), math.sqrt(2.0), math.PI
+// the int functions error instead of overflowing, the float functions follow IEEE 754 (math.sqrt(-1.0) is NaN)
+
+use super::{float, int, native_name, Member};
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let (params, ret) = match name {
+        "abs" => (vec![Type::Int], Type::Int),
+        "min" => (vec![Type::Int, Type::Int], Type::Int),
+        "max" => (vec![Type::Int, Type::Int], Type::Int),
+        "pow" => (vec![Type::Int, Type::Int], Type::Int),
+        "gcd" => (vec![Type::Int, Type::Int], Type::Int),
+        "lcm" => (vec![Type::Int, Type::Int], Type::Int),
+        "clamp" => (vec![Type::Int, Type::Int, Type::Int], Type::Int),
+        "isqrt" => (vec![Type::Int], Type::Int),
+        "sqrt" => (vec![Type::Float], Type::Float),
+        "sin" => (vec![Type::Float], Type::Float),
+        "floor" => (vec![Type::Float], Type::Int),
+        "ceil" => (vec![Type::Float], Type::Int),
+        "PI" => return Some(Member::Constant(Type::Float)),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+fn overflow(name: &str) -> String {
+    format!("The result of math.{} does not fit in a 64-bit integer.", name)
+}
+
+fn gcd(a: i64, b: i64) -> Result<i64, String> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i64::try_from(a).map_err(|_| overflow("gcd"))
+}
+
+// floor and ceil give back an int, so the float has to fit in one
+fn float_to_int(name: &str, value: f64) -> Result<i64, String> {
+    if value.is_nan() || value < i64::MIN as f64 || value >= i64::MAX as f64 {
+        return Err(format!("math.{} can't turn {:?} into an int.", name, value));
+    }
+    Ok(value as i64)
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("math", member);
+
+    vm.register_native(&name("abs"), 1, |args| {
+        int(args, 0)?.checked_abs().map(Value::Int).ok_or_else(|| overflow("abs"))
+    });
+    vm.register_native(&name("min"), 2, |args| Ok(Value::Int(int(args, 0)?.min(int(args, 1)?))));
+    vm.register_native(&name("max"), 2, |args| Ok(Value::Int(int(args, 0)?.max(int(args, 1)?))));
+    vm.register_native(&name("pow"), 2, |args| {
+        let (base, exponent) = (int(args, 0)?, int(args, 1)?);
+        let exponent = u32::try_from(exponent).map_err(|_| format!("math.pow needs an exponent between 0 and {}, got {}.", u32::MAX, exponent))?;
+        base.checked_pow(exponent).map(Value::Int).ok_or_else(|| overflow("pow"))
+    });
+    vm.register_native(&name("gcd"), 2, |args| Ok(Value::Int(gcd(int(args, 0)?, int(args, 1)?)?)));
+    vm.register_native(&name("lcm"), 2, |args| {
+        let (a, b) = (int(args, 0)?, int(args, 1)?);
+        if a == 0 || b == 0 {
+            return Ok(Value::Int(0));
+        }
+        (a / gcd(a, b)?).checked_mul(b).and_then(i64::checked_abs).map(Value::Int).ok_or_else(|| overflow("lcm"))
+    });
+    vm.register_native(&name("clamp"), 3, |args| {
+        let (value, min, max) = (int(args, 0)?, int(args, 1)?, int(args, 2)?);
+        if min > max {
+            return Err(format!("math.clamp needs min to be less than or equal to max, got {} and {}.", min, max));
+        }
+        Ok(Value::Int(value.clamp(min, max)))
+    });
+    vm.register_native(&name("isqrt"), 1, |args| {
+        let value = int(args, 0)?;
+        if value < 0 {
+            return Err(format!("math.isqrt can't take the square root of a negative number, got {}.", value));
+        }
+        Ok(Value::Int(value.isqrt()))
+    });
+    vm.register_native(&name("sqrt"), 1, |args| Ok(Value::Float(float(args, 0)?.sqrt())));
+    vm.register_native(&name("sin"), 1, |args| Ok(Value::Float(float(args, 0)?.sin())));
+    vm.register_native(&name("floor"), 1, |args| Ok(Value::Int(float_to_int("floor", float(args, 0)?.floor())?)));
+    vm.register_native(&name("ceil"), 1, |args| Ok(Value::Int(float_to_int("ceil", float(args, 0)?.ceil())?)));
+    vm.register_native(&name("PI"), 0, |_| Ok(Value::Float(std::f64::consts::PI)));
+}
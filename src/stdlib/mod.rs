@@ -0,0 +1,99 @@
+// THE STANDARD LIBRARY
+// modules every program can use without importing them, like math.sqrt(2.0)
+// the checker and generator get the signatures from here, and the VM registers the implementations as native functions named "module.member"
+
+pub mod fs;
+pub mod json;
+pub mod list;
+pub mod map;
+pub mod math;
+pub mod random;
+pub mod string;
+pub mod time;
+
+use crate::interpreter::value::mismatch;
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+/// A member of a standard library module.
+pub enum Member {
+    /// Called like a function, `math.pow(2, 10)`.
+    Function { params: Vec<Type>, ret: Type },
+    /// Used like a value, `math.PI`. It's still a native function at runtime, one that takes no arguments and is used without parenthesis.
+    Constant(Type),
+}
+
+pub const MODULES: &[&str] = &["fs", "json", "list", "map", "math", "random", "string", "time"];
+
+pub fn is_module(name: &str) -> bool {
+    MODULES.contains(&name)
+}
+
+/// Looks up a member of a module, `None` if either doesn't exist.
+pub fn member(module: &str, name: &str) -> Option<Member> {
+    match module {
+        "fs" => fs::member(name),
+        "json" => json::member(name),
+        "list" => list::member(name),
+        "map" => map::member(name),
+        "math" => math::member(name),
+        "random" => random::member(name),
+        "string" => string::member(name),
+        "time" => time::member(name),
+        _ => None,
+    }
+}
+
+/// The name a member's native function is registered under.
+pub fn native_name(module: &str, name: &str) -> String {
+    format!("{}.{}", module, name)
+}
+
+/// Registers the native functions of every module, `VM::new` does this for every VM.
+pub fn register(vm: &mut VM) {
+    fs::register(vm);
+    json::register(vm);
+    list::register(vm);
+    map::register(vm);
+    math::register(vm);
+    random::register(vm);
+    string::register(vm);
+    time::register(vm);
+}
+
+// argument helpers for the natives, the checker already made sure the types are right but the bytecode could have been written by anything
+// strings and lists are borrowed, so looking at an argument doesn't copy it
+fn int(args: &[Value], index: usize) -> Result<i64, String> {
+    args[index].clone().try_into()
+}
+
+fn float(args: &[Value], index: usize) -> Result<f64, String> {
+    args[index].clone().try_into()
+}
+
+fn bool(args: &[Value], index: usize) -> Result<bool, String> {
+    args[index].clone().try_into()
+}
+
+fn str(args: &[Value], index: usize) -> Result<&str, String> {
+    as_str(&args[index])
+}
+
+fn as_str(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::Str(value) => Ok(value),
+        other => Err(mismatch("str", other)),
+    }
+}
+
+// the arguments of a VM native by value, CALL_NATIVE already made sure there are as many as it was registered with
+fn take<const N: usize>(args: Vec<Value>) -> [Value; N] {
+    args.try_into().unwrap_or_else(|args: Vec<Value>| panic!("expected {} arguments, got {}", N, args.len()))
+}
+
+fn list(args: &[Value], index: usize) -> Result<&[Value], String> {
+    match &args[index] {
+        Value::List(items) => Ok(items),
+        other => Err(mismatch("list", other)),
+    }
+}
@@ -0,0 +1,111 @@
+// random.rand_int(1, 6), random.shuffle(xs), random.choice(xs)
+// the numbers come from a SplitMix64 generator in the VM, which gives the same numbers for the same seed on every machine
+// the seed comes from the OS clock unless the host picks one with VM::set_seed (cobalt run --seed N)
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{native_name, take, Member};
+use crate::errors::VmError;
+use crate::interpreter::value::mismatch;
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let t = Type::Param("T");
+    let list = |item: &Type| Type::List(Box::new(item.clone()));
+
+    let (params, ret) = match name {
+        "rand_int" => (vec![Type::Int, Type::Int], Type::Int),
+        "shuffle" => (vec![list(&t)], list(&t)),
+        "choice" => (vec![list(&t)], Type::Optional(Box::new(t))),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+/// A small deterministic pseudo-random number generator (SplitMix64). Not meant for anything security related.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeded from the OS clock, so every run gets different numbers.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`, every one equally likely. `bound` can't be 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // numbers from the last incomplete run of `bound` would make the small results more likely, so they are thrown away
+        let zone = u64::MAX - (u64::MAX - bound + 1) % bound;
+        loop {
+            let number = self.next_u64();
+            if number <= zone {
+                return number % bound;
+            }
+        }
+    }
+}
+
+fn take_list(vm: &VM, value: Value) -> Result<Vec<Value>, VmError> {
+    match value {
+        Value::List(items) => Ok(items),
+        other => Err(vm.error(mismatch("list", &other))),
+    }
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("random", member);
+
+    vm.register_vm_native(&name("rand_int"), 2, |vm, args| {
+        // both ends are included, rand_int(1, 6) is a dice roll
+        let [lo, hi] = take(args);
+        let lo = i64::try_from(lo).map_err(|msg| vm.error(msg))?;
+        let hi = i64::try_from(hi).map_err(|msg| vm.error(msg))?;
+        if lo > hi {
+            return Err(vm.error(format!("random.rand_int got a lower bound ({}) bigger than its upper bound ({})", lo, hi)));
+        }
+
+        let span = hi.abs_diff(lo);
+        let offset = match span.checked_add(1) {
+            Some(bound) => vm.rng().below(bound),
+            None => vm.rng().next_u64(), // every i64 is possible
+        };
+        Ok(Value::Int(lo.wrapping_add_unsigned(offset)))
+    });
+    vm.register_vm_native(&name("shuffle"), 1, |vm, args| {
+        // Fisher-Yates
+        let [list] = take(args);
+        let mut items = take_list(vm, list)?;
+        for i in (1..items.len()).rev() {
+            let j = vm.rng().below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+        Ok(Value::List(items))
+    });
+    vm.register_vm_native(&name("choice"), 1, |vm, args| {
+        // none for an empty list, like list.get
+        let [list] = take(args);
+        let mut items = take_list(vm, list)?;
+        if items.is_empty() {
+            return Ok(Value::None);
+        }
+        let index = vm.rng().below(items.len() as u64) as usize;
+        Ok(items.swap_remove(index))
+    });
+}
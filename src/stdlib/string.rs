@@ -0,0 +1,88 @@
+// string.upper("hi"), string.split("a,b", ","), string.find("hello", "l")
+// lengths and positions count characters, not bytes, so string.len("héllo") is 5
+
+use super::{as_str, int, list, native_name, str, Member};
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let list_of_str = || Type::List(Box::new(Type::Str));
+
+    let (params, ret) = match name {
+        "len" => (vec![Type::Str], Type::Int),
+        "upper" => (vec![Type::Str], Type::Str),
+        "lower" => (vec![Type::Str], Type::Str),
+        "trim" => (vec![Type::Str], Type::Str),
+        "split" => (vec![Type::Str, Type::Str], list_of_str()),
+        "join" => (vec![list_of_str(), Type::Str], Type::Str),
+        "contains" => (vec![Type::Str, Type::Str], Type::Bool),
+        "starts_with" => (vec![Type::Str, Type::Str], Type::Bool),
+        "replace" => (vec![Type::Str, Type::Str, Type::Str], Type::Str),
+        "find" => (vec![Type::Str, Type::Str], Type::Optional(Box::new(Type::Int))),
+        "repeat" => (vec![Type::Str, Type::Int], Type::Str),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+// split and replace would do something surprising with an empty pattern, so it's an error instead
+fn pattern<'a>(name: &str, args: &'a [Value], index: usize) -> Result<&'a str, String> {
+    let pattern = str(args, index)?;
+    if pattern.is_empty() {
+        return Err(format!("string.{} can't be given an empty string to look for.", name));
+    }
+    Ok(pattern)
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("string", member);
+
+    vm.register_native(&name("len"), 1, |args| Ok(Value::Int(str(args, 0)?.chars().count() as i64)));
+    vm.register_native(&name("upper"), 1, |args| Ok(Value::Str(str(args, 0)?.to_uppercase())));
+    vm.register_native(&name("lower"), 1, |args| Ok(Value::Str(str(args, 0)?.to_lowercase())));
+    vm.register_native(&name("trim"), 1, |args| Ok(Value::Str(str(args, 0)?.trim().to_string())));
+    vm.register_native(&name("split"), 2, |args| {
+        let separator = pattern("split", args, 1)?;
+        Ok(Value::List(str(args, 0)?.split(separator).map(Value::from).collect()))
+    });
+    vm.register_native(&name("join"), 2, |args| {
+        let separator = str(args, 1)?;
+        let mut joined = String::new();
+        for (i, item) in list(args, 0)?.iter().enumerate() {
+            if i > 0 {
+                joined.push_str(separator);
+            }
+            joined.push_str(as_str(item)?);
+        }
+        Ok(Value::Str(joined))
+    });
+    vm.register_native(&name("contains"), 2, |args| Ok(Value::Bool(str(args, 0)?.contains(str(args, 1)?))));
+    vm.register_native(&name("starts_with"), 2, |args| Ok(Value::Bool(str(args, 0)?.starts_with(str(args, 1)?))));
+    vm.register_native(&name("replace"), 3, |args| {
+        let from = pattern("replace", args, 1)?;
+        Ok(Value::Str(str(args, 0)?.replace(from, str(args, 2)?)))
+    });
+    vm.register_native(&name("find"), 2, |args| {
+        let text = str(args, 0)?;
+        // the byte offset Rust gives back is turned into a character position
+        let position = text.find(str(args, 1)?).map(|offset| text[..offset].chars().count() as i64);
+        Ok(Value::from(position))
+    });
+    vm.register_native(&name("repeat"), 2, |args| {
+        let (text, count) = (str(args, 0)?, int(args, 1)?);
+        let count = usize::try_from(count).map_err(|_| format!("string.repeat can't repeat a string {} times.", count))?;
+        // a String can't be longer than isize::MAX bytes, and the memory is reserved up front so running out of it is an error instead of an abort
+        let too_long = || "The result of string.repeat is too long.".to_string();
+        let length = text.len().checked_mul(count).filter(|length| *length <= isize::MAX as usize).ok_or_else(too_long)?;
+        if length == 0 {
+            return Ok(Value::Str(String::new()));
+        }
+        let mut result = String::new();
+        result.try_reserve_exact(length).map_err(|_| too_long())?;
+        for _ in 0..count {
+            result.push_str(text);
+        }
+        Ok(Value::Str(result))
+    });
+}
@@ -0,0 +1,36 @@
+// time.now_ms(), time.monotonic_ns(), time.sleep(ms)
+// the VM's clock gives the times, the host can swap it for a fake one with VM::set_clock (cobalt run --fake-time) so runs are reproducible
+
+use super::{native_name, take, Member};
+use crate::interpreter::value::mismatch;
+use crate::interpreter::vm::{Value, VM};
+use crate::typechecker::types::Type;
+
+pub fn member(name: &str) -> Option<Member> {
+    let (params, ret) = match name {
+        "now_ms" => (vec![], Type::Int),
+        "monotonic_ns" => (vec![], Type::Int),
+        "sleep" => (vec![Type::Int], Type::Void),
+        _ => return None,
+    };
+
+    Some(Member::Function { params, ret })
+}
+
+pub fn register(vm: &mut VM) {
+    let name = |member| native_name("time", member);
+
+    vm.register_vm_native(&name("now_ms"), 0, |vm, _| Ok(Value::Int(vm.clock().now_ms())));
+    vm.register_vm_native(&name("monotonic_ns"), 0, |vm, _| Ok(Value::Int(vm.clock().monotonic_ns())));
+    vm.register_vm_native(&name("sleep"), 1, |vm, args| {
+        let [ms] = take(args);
+        let Value::Int(ms) = ms else {
+            return Err(vm.error(mismatch("int", &ms)));
+        };
+        if ms < 0 {
+            return Err(vm.error(format!("time.sleep can't sleep for a negative amount of time ({}ms)", ms)));
+        }
+        vm.clock().sleep(ms);
+        Ok(Value::None)
+    });
+}
@@ -0,0 +1,876 @@
+// walks the AST after parsing and gives every expression a static type
+// declarations without an annotation get their type inferred from their value (synthesis),
+// declarations with an annotation have their value checked against it (checking)
+
+use std::collections::HashMap;
+
+use super::types::Type;
+use crate::errors::{Diagnostic, Stage};
+use crate::parser::ast;
+use crate::stdlib::{self, Member};
+
+// the functions that synth_builtin_call knows about, they can't be redeclared as extern functions
+const BUILTINS: &[&str] = &["print", "println", "read_line", "input", "parse_int", "args", "env", "exit", "send", "receive"];
+
+// variables and types live in seperate namespaces, so a variable and a type can share a name
+#[derive(Default)]
+struct Scope {
+    vars: HashMap<String, Binding>,
+    types: HashMap<String, Binding>, // aliases are bound to the type they stand for, newtypes are bound to themselves
+}
+
+struct Binding {
+    r#type: Type,
+    deprecated: Option<String>, // set by @deprecated, holds the reason (which can be empty)
+    native: bool, // extern functions are called by name, so unlike lambdas they can't be used as values
+}
+
+pub struct Checker {
+    scopes: Vec<Scope>,
+    inferred: Vec<(String, Type)>, // every declaration and the type it ended up with, in source order (used by --debug)
+    tests: Vec<String>, // declarations marked with @test, in source order
+    generator: Option<Type>, // the type of the values the generator being checked yields, yield can't be used outside of one
+    depth: usize, // how deeply the statement or expression being checked is nested, see nest
+    warnings: Vec<Diagnostic>,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Scope::default()],
+            inferred: Vec::new(),
+            tests: Vec::new(),
+            generator: None,
+            depth: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Returns every variable declaration that was checked along with its type, in the order they were declared.
+    pub fn inferred(&self) -> &[(String, Type)] {
+        &self.inferred
+    }
+
+    /// Returns the names of every declaration marked with `@test`, in the order they were declared.
+    pub fn tests(&self) -> &[String] {
+        &self.tests
+    }
+
+    /// Returns the warnings found so far, in the order they were found.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    fn error(&self, msg: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(Stage::Checker, msg)
+    }
+
+    // the parser already limits how deeply code is nested, this keeps an AST that was built some other way from running out of stack
+    fn nest(&mut self) -> Result<(), Diagnostic> {
+        if self.depth == ast::MAX_DEPTH {
+            return Err(self.error(format!("The code is nested too deeply, it can be at most {} levels deep.", ast::MAX_DEPTH)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn warn(&mut self, msg: &str) {
+        self.warnings.push(Diagnostic::warning(Stage::Checker, msg));
+    }
+
+    // warns every time something marked with @deprecated is used
+    fn warn_deprecated(&mut self, what: &str, ident: &str, deprecated: Option<String>) {
+        match deprecated.as_deref() {
+            Some("") => self.warn(&format!("{} '{}' is deprecated.", what, ident)),
+            Some(reason) => self.warn(&format!("{} '{}' is deprecated: {}", what, ident, reason)),
+            None => {}
+        }
+    }
+
+    fn declare(&mut self, ident: &str, r#type: Type, deprecated: Option<String>) {
+        self.scopes.last_mut().unwrap().vars.insert(ident.to_string(), Binding { r#type, deprecated, native: false });
+    }
+
+    fn find(&self, ident: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.vars.get(ident))
+    }
+
+    fn lookup(&mut self, ident: &str) -> Result<Type, Diagnostic> {
+        let Some(binding) = self.find(ident) else {
+            return Err(self.error(format!("Variable '{}' does not exist in any accessible scope.", ident)));
+        };
+
+        let (r#type, deprecated) = (binding.r#type.clone(), binding.deprecated.clone());
+        self.warn_deprecated("Variable", ident, deprecated);
+        Ok(r#type)
+    }
+
+    fn declare_type(&mut self, ident: &str, r#type: Type, deprecated: Option<String>) -> Result<(), Diagnostic> {
+        if self.scopes.last().unwrap().types.contains_key(ident) || Type::from_keyword(ident).is_some() {
+            return Err(self.error(format!("Type '{}' already exists in the current scope.", ident)));
+        }
+
+        self.scopes.last_mut().unwrap().types.insert(ident.to_string(), Binding { r#type, deprecated, native: false });
+        Ok(())
+    }
+
+    fn lookup_type(&mut self, ident: &str) -> Result<Type, Diagnostic> {
+        if let Some(r#type) = Type::from_keyword(ident) {
+            return Ok(r#type);
+        }
+
+        let Some(binding) = self.scopes.iter().rev().find_map(|scope| scope.types.get(ident)) else {
+            // json isn't a keyword, so a type declared with that name hides it
+            if ident == "json" {
+                return Ok(Type::Json);
+            }
+            return Err(self.error(format!("Type '{}' does not exist in any accessible scope.", ident)));
+        };
+
+        let (r#type, deprecated) = (binding.r#type.clone(), binding.deprecated.clone());
+        self.warn_deprecated("Type", ident, deprecated);
+        Ok(r#type)
+    }
+
+    // turns the names written in annotations into the types they refer to
+    fn resolve_type(&mut self, r#type: &Type) -> Result<Type, Diagnostic> {
+        Ok(match r#type {
+            Type::Named(name) => self.lookup_type(name)?,
+            Type::Optional(inner) => Type::Optional(Box::new(self.resolve_type(inner)?)),
+            Type::List(inner) => Type::List(Box::new(self.resolve_type(inner)?)),
+            Type::Generator(inner) => Type::Generator(Box::new(self.resolve_type(inner)?)),
+            Type::Channel(inner) => Type::Channel(Box::new(self.resolve_type(inner)?)),
+            Type::Map(key, value) => {
+                let key = self.resolve_type(key)?;
+                if !key.is_key() {
+                    return Err(self.error(format!("Values of type '{}' can't be used as map keys, only int, str and bool can.", key)));
+                }
+                Type::Map(Box::new(key), Box::new(self.resolve_type(value)?))
+            }
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|item| self.resolve_type(item)).collect::<Result<_, _>>()?),
+            Type::Function { params, ret } => Type::Function {
+                params: params.iter().map(|param| self.resolve_type(param)).collect::<Result<_, _>>()?,
+                ret: Box::new(self.resolve_type(ret)?),
+            },
+            other => other.clone(),
+        })
+    }
+
+    // the type an operator produces from its operands, shared by binary expressions and compound assignments
+    fn operator_type(&self, operator: &str, left: &Type, right: &Type) -> Result<Type, Diagnostic> {
+        // newtypes support the operators of the type they wrap, but only with values of the same newtype
+        if let (Type::Newtype { inner, .. }, true) = (left, left == right) {
+            let result = self.operator_type(operator, inner, inner)?;
+            return Ok(match operator {
+                "==" | "!=" | "<" | ">" | "<=" | ">=" => result,
+                _ => left.clone(),
+            });
+        }
+
+        let result = match (operator, left, right) {
+            ("+" | "+=", Type::Int, Type::Int) => Some(Type::Int),
+            ("+" | "+=", Type::Str, Type::Str) => Some(Type::Str),
+            ("-" | "-=" | "*" | "/" | "%", Type::Int, Type::Int) => Some(Type::Int),
+            ("+" | "+=" | "-" | "-=" | "*" | "/", Type::Float, Type::Float) => Some(Type::Float),
+            ("==" | "!=", l, r) if l == r => Some(Type::Bool),
+            ("==" | "!=", Type::Optional(l), r) if **l == *r => Some(Type::Bool),
+            ("==" | "!=", l, Type::Optional(r)) if *l == **r => Some(Type::Bool),
+            ("<" | ">" | "<=" | ">=", Type::Int, Type::Int) => Some(Type::Bool),
+            ("<" | ">" | "<=" | ">=", Type::Float, Type::Float) => Some(Type::Bool),
+            _ => None,
+        };
+
+        result.ok_or_else(|| self.error(format!("Mismatched or unsupported types on '{}' operation of type '{}' and '{}'.", operator, left, right)))
+    }
+
+    // infers the type of an expression from its contents
+    fn synth_expr(&mut self, expr: &ast::Expr) -> Result<Type, Diagnostic> {
+        self.nest()?;
+        let r#type = self.synth_expr_kind(expr);
+        self.depth -= 1;
+        r#type
+    }
+
+    fn synth_expr_kind(&mut self, expr: &ast::Expr) -> Result<Type, Diagnostic> {
+        Ok(match expr {
+            ast::Expr::NumericLiteral(_) => Type::Int,
+            ast::Expr::FloatLiteral(_) => Type::Float,
+            ast::Expr::BooleanLiteral(_) => Type::Bool,
+            ast::Expr::StringLiteral(_) => Type::Str,
+            ast::Expr::NoneLiteral(_) => {
+                return Err(self.error("The type of 'none' can't be inferred here, give the variable a type annotation (let x: str? = none)."));
+            }
+            ast::Expr::Identifier(identifier) => {
+                if self.find(&identifier.symbol).is_some_and(|binding| binding.native) {
+                    return Err(self.error(format!("Function '{}' can only be called, it can't be used as a value.", identifier.symbol)));
+                }
+                self.lookup(&identifier.symbol)?
+            }
+            ast::Expr::ListLiteral(list) => {
+                let Some((first, rest)) = list.items.split_first() else {
+                    return Err(self.error("The type of an empty list can't be inferred here, give the variable a type annotation (let x: list<int> = [])."));
+                };
+
+                let item = self.synth_expr(first)?;
+                if item == Type::Void {
+                    return Err(self.error("Lists can't hold values of type 'void'."));
+                }
+                for other in rest {
+                    self.check_expr(other, &item)?;
+                }
+                Type::List(Box::new(item))
+            }
+            ast::Expr::MapLiteral(map) => {
+                let Some(((first_key, first_value), rest)) = map.entries.split_first() else {
+                    return Err(self.error("The type of an empty map can't be inferred here, give the variable a type annotation (let x: map<str, int> = {})."));
+                };
+
+                let key = self.synth_expr(first_key)?;
+                if !key.is_key() {
+                    return Err(self.error(format!("Values of type '{}' can't be used as map keys, only int, str and bool can.", key)));
+                }
+                let value = self.synth_expr(first_value)?;
+                if value == Type::Void {
+                    return Err(self.error("Maps can't hold values of type 'void'."));
+                }
+                for (other_key, other_value) in rest {
+                    self.check_expr(other_key, &key)?;
+                    self.check_expr(other_value, &value)?;
+                }
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            ast::Expr::TupleLiteral(tuple) => {
+                if tuple.items.len() > u8::MAX as usize {
+                    return Err(self.error(format!("Tuples can have at most {} items.", u8::MAX)));
+                }
+
+                let mut items = Vec::new();
+                for item in &tuple.items {
+                    let r#type = self.synth_expr(item)?;
+                    if r#type == Type::Void {
+                        return Err(self.error("Tuples can't hold values of type 'void'."));
+                    }
+                    items.push(r#type);
+                }
+                Type::Tuple(items)
+            }
+            ast::Expr::Lambda(lambda) => self.synth_lambda(lambda)?,
+            ast::Expr::ChannelExpr(channel) => Type::Channel(Box::new(self.resolve_type(&channel.r#type)?)),
+            ast::Expr::Binary(binary_expr) => {
+                // comparing with none (x == none) works for any optional
+                let none_side = match (&*binary_expr.left, &*binary_expr.right) {
+                    (ast::Expr::NoneLiteral(_), other) | (other, ast::Expr::NoneLiteral(_)) => Some(other),
+                    _ => None,
+                };
+
+                if let Some(other) = none_side && matches!(binary_expr.operator.as_str(), "==" | "!=") {
+                    let other = self.synth_expr(other)?;
+                    if !matches!(other, Type::Optional(_)) {
+                        return Err(self.error(format!("Only optional values can be compared with none, but found a value of type '{}'.", other)));
+                    }
+                    return Ok(Type::Bool);
+                }
+
+                let left = self.synth_expr(&binary_expr.left)?;
+                let right = self.synth_expr(&binary_expr.right)?;
+                self.operator_type(&binary_expr.operator, &left, &right)?
+            }
+            ast::Expr::LogicalExpr(logical_expr) if logical_expr.operator == "??" => {
+                // the fallback can be another optional (a ?? b ?? "c"), then the result is still optional
+                let left = self.synth_expr(&logical_expr.left)?;
+                let Type::Optional(inner) = left else {
+                    return Err(self.error(format!("The left hand side of '??' must be optional, but found a value of type '{}'.", left)));
+                };
+
+                if let ast::Expr::NoneLiteral(_) = &*logical_expr.right {
+                    return Ok(Type::Optional(inner));
+                }
+
+                let right = self.synth_expr(&logical_expr.right)?;
+                if right == Type::Optional(inner.clone()) {
+                    right
+                } else if right == *inner {
+                    *inner
+                } else {
+                    return Err(self.error(format!("The fallback for a value of type '{}' must be of type '{}', but found a value of type '{}'.", Type::Optional(inner.clone()), inner, right)));
+                }
+            }
+            ast::Expr::LogicalExpr(logical_expr) => {
+                self.check_expr(&logical_expr.left, &Type::Bool)?;
+                self.check_expr(&logical_expr.right, &Type::Bool)?;
+                Type::Bool
+            }
+            ast::Expr::UnaryExpr(unary_expr) if unary_expr.operator == "!" => {
+                self.check_expr(&unary_expr.value, &Type::Bool)?;
+                Type::Bool
+            }
+            ast::Expr::UnaryExpr(unary_expr) => {
+                let value = self.synth_expr(&unary_expr.value)?;
+                if !matches!(value, Type::Int | Type::Float) {
+                    return Err(self.error(format!("Unary '{}' only works on numbers, but found a value of type '{}'.", unary_expr.operator, value)));
+                }
+                value
+            }
+            ast::Expr::Call(call_expr) => self.synth_call_expr(call_expr)?,
+            ast::Expr::Member(member_expr) if self.is_module(&member_expr.object) => match self.module_member(member_expr)? {
+                (_, Member::Constant(r#type)) => r#type,
+                (module, Member::Function { .. }) => {
+                    return Err(self.error(format!("Function '{}.{}' can only be called, it can't be used as a value.", module, member_expr.property)));
+                }
+            },
+            ast::Expr::Member(member_expr) => {
+                // pair.0 is the first item of a tuple
+                let object = self.synth_expr(&member_expr.object)?;
+                let Type::Tuple(items) = &object else {
+                    return Err(self.error(format!("Values of type '{}' don't have members, '{}' can't be accessed.", object, member_expr.property)));
+                };
+
+                match member_expr.property.parse::<usize>().ok().and_then(|index| items.get(index)) {
+                    Some(item) => item.clone(),
+                    None => return Err(self.error(format!("Tuple of type '{}' has no item '{}'.", object, member_expr.property))),
+                }
+            }
+            ast::Expr::AssignmentExpr(assignment_expr) => {
+                if !matches!(&*assignment_expr.assignee, ast::Expr::Identifier(_)) {
+                    return Err(self.error("Only variables can be assigned to."));
+                }
+
+                let assignee = self.synth_expr(&assignment_expr.assignee)?;
+
+                if assignment_expr.operator == "=" {
+                    self.check_expr(&assignment_expr.value, &assignee)?;
+                } else {
+                    let value = self.synth_expr(&assignment_expr.value)?;
+                    let result = self.operator_type(&assignment_expr.operator, &assignee, &value)?;
+                    if result != assignee {
+                        return Err(self.error(format!("Cannot assign a value of type '{}' to a variable of type '{}'.", result, assignee)));
+                    }
+                }
+
+                // the value is stored and nothing is left behind, so an assignment can't be used as a value (let y = x = 5)
+                Type::Void
+            }
+        })
+    }
+
+    // makes sure a call got the right amount of arguments, with the right types
+    fn check_args(&mut self, name: &str, args: &[ast::Expr], params: &[Type]) -> Result<(), Diagnostic> {
+        if args.len() != params.len() {
+            return Err(self.error(format!("'{}' takes {} argument(s), but {} were given.", name, params.len(), args.len())));
+        }
+
+        for (arg, param) in args.iter().zip(params) {
+            self.check_expr(arg, param)?;
+        }
+
+        Ok(())
+    }
+
+    // print(a, b) and println(a, b) write any amount of values to the output
+    // read_line() reads a line from the input, input(prompt) prints the prompt first, both give none once there is nothing left to read
+    // parse_int(text) turns text into an int, none if it isn't one
+    // args() gives the arguments the program was run with, env(name) gives an environment variable or none if it isn't set
+    // exit(code) stops the program with that exit code
+    // send(channel, value) gives a value to whichever task receives from the channel, receive(channel) waits for one
+    fn synth_builtin_call(&mut self, name: &str, args: &[ast::Expr]) -> Result<Option<Type>, Diagnostic> {
+        Ok(match name {
+            "print" | "println" => {
+                if args.len() > u8::MAX as usize {
+                    return Err(self.error(format!("'{}' can be given at most {} arguments.", name, u8::MAX)));
+                }
+
+                for arg in args {
+                    if self.synth_expr(arg)? == Type::Void {
+                        return Err(self.error(format!("Cannot pass a value of type 'void' to '{}'.", name)));
+                    }
+                }
+
+                Some(Type::Void)
+            }
+            "read_line" => {
+                self.check_args(name, args, &[])?;
+                Some(Type::Optional(Box::new(Type::Str)))
+            }
+            "input" => {
+                self.check_args(name, args, &[Type::Str])?;
+                Some(Type::Optional(Box::new(Type::Str)))
+            }
+            "parse_int" => {
+                self.check_args(name, args, &[Type::Str])?;
+                Some(Type::Optional(Box::new(Type::Int)))
+            }
+            "args" => {
+                self.check_args(name, args, &[])?;
+                Some(Type::List(Box::new(Type::Str)))
+            }
+            "env" => {
+                self.check_args(name, args, &[Type::Str])?;
+                Some(Type::Optional(Box::new(Type::Str)))
+            }
+            "exit" => {
+                self.check_args(name, args, &[Type::Int])?;
+                Some(Type::Void)
+            }
+            "send" | "receive" => {
+                let expected = if name == "send" { 2 } else { 1 };
+                if args.len() != expected {
+                    return Err(self.error(format!("'{}' takes {} argument(s), but {} were given.", name, expected, args.len())));
+                }
+
+                let Type::Channel(values) = self.synth_expr(&args[0])? else {
+                    return Err(self.error(format!("The first argument of '{}' has to be a channel (channel<int>()).", name)));
+                };
+
+                if name == "send" {
+                    self.check_expr(&args[1], &values)?;
+                    Some(Type::Void)
+                } else {
+                    Some(*values)
+                }
+            }
+            _ => None,
+        })
+    }
+
+    // modules are always in scope (there is nothing to import), but a variable with the same name hides the module
+    fn is_module(&self, object: &ast::Expr) -> bool {
+        matches!(object, ast::Expr::Identifier(identifier) if stdlib::is_module(&identifier.symbol) && self.find(&identifier.symbol).is_none())
+    }
+
+    // finds the standard library member that math.sqrt refers to
+    fn module_member(&self, member_expr: &ast::MemberExpr) -> Result<(String, Member), Diagnostic> {
+        let module = match &*member_expr.object {
+            ast::Expr::Identifier(identifier) if self.is_module(&member_expr.object) => &identifier.symbol,
+            _ => return Err(self.error(format!("Only modules have members, '{}' can't be accessed here.", member_expr.property))),
+        };
+
+        match stdlib::member(module, &member_expr.property) {
+            Some(member) => Ok((module.clone(), member)),
+            None => Err(self.error(format!("Module '{}' has no member named '{}'.", module, member_expr.property))),
+        }
+    }
+
+    // fn(x: int): int => x * 2, the parameters are only in scope inside the body
+    fn synth_lambda(&mut self, lambda: &ast::Lambda) -> Result<Type, Diagnostic> {
+        if lambda.params.len() > u8::MAX as usize {
+            return Err(self.error(format!("Lambdas can have at most {} parameters.", u8::MAX)));
+        }
+
+        self.scopes.push(Scope::default());
+
+        let mut params = Vec::new();
+        for param in &lambda.params {
+            let r#type = self.resolve_type(&param.r#type)?;
+            if r#type == Type::Void {
+                return Err(self.error(format!("Parameter '{}' can't be of type 'void'.", param.name)));
+            }
+            if self.scopes.last().unwrap().vars.contains_key(&param.name) {
+                return Err(self.error(format!("Parameter '{}' is declared more than once.", param.name)));
+            }
+            self.declare(&param.name, r#type.clone(), None);
+            params.push(r#type);
+        }
+
+        let return_type = lambda.return_type.as_ref().map(|annotation| self.resolve_type(annotation)).transpose()?;
+        let outer_generator = self.generator.take();
+
+        let ret = match &lambda.body {
+            // checking a statement fills in the types of its declarations, but lambdas are expressions, so block bodies are checked on a copy
+            ast::LambdaBody::Block(block) => match return_type {
+                Some(Type::Generator(values)) => {
+                    self.generator = Some(*values.clone());
+                    self.check_block_stmt(&mut block.clone())?;
+                    Type::Generator(values)
+                }
+                None => {
+                    self.check_block_stmt(&mut block.clone())?;
+                    Type::Void
+                }
+                Some(other) => {
+                    return Err(self.error(format!("A lambda with a block as its body gives back nothing, or a gen<T> if it's a generator, but it has return type '{}'.", other)));
+                }
+            },
+            ast::LambdaBody::Expr(body) => {
+                // the body's value is what the lambda gives back, so it needs to have one
+                if let ast::Expr::AssignmentExpr(_) = &**body {
+                    return Err(self.error("The body of a lambda can't be an assignment, it has to give back a value."));
+                }
+
+                let ret = match return_type {
+                    Some(annotation) => {
+                        self.check_expr(body, &annotation)?;
+                        annotation
+                    }
+                    None => self.synth_expr(body)?,
+                };
+
+                if ret == Type::Void {
+                    return Err(self.error("The body of a lambda after '=>' has to give back a value, but it has type 'void'. Use a block for lambdas that give back nothing (fn() { println(1) })."));
+                }
+                ret
+            }
+        };
+
+        self.generator = outer_generator;
+        self.scopes.pop();
+        Ok(Type::Function { params, ret: Box::new(ret) })
+    }
+
+    // like check_args, but the parameters can have type parameters (list<T>) that are bound to the types of the arguments
+    // each argument is checked against what is already known, so in list.map(xs, fn(x: int): str => ...) T comes from xs
+    fn check_generic_args(&mut self, name: &str, args: &[ast::Expr], params: &[Type], ret: &Type) -> Result<Type, Diagnostic> {
+        if args.len() != params.len() {
+            return Err(self.error(format!("'{}' takes {} argument(s), but {} were given.", name, params.len(), args.len())));
+        }
+
+        let mut bound = HashMap::new();
+        for (arg, param) in args.iter().zip(params) {
+            let param = param.substitute(&bound);
+            if !param.has_params() {
+                self.check_expr(arg, &param)?;
+                continue;
+            }
+
+            let found = self.synth_expr(arg)?;
+            if !param.unify(&found, &mut bound) {
+                return Err(self.error(format!("Expected a value of type '{}', but found a value of type '{}'.", param.substitute(&bound), found)));
+            }
+        }
+
+        Ok(ret.substitute(&bound))
+    }
+
+    // calls a value that isn't a name, like a tuple item or the result of another call
+    fn synth_value_call(&mut self, callee: &ast::Expr, args: &[ast::Expr]) -> Result<Type, Diagnostic> {
+        let Type::Function { params, ret } = self.synth_expr(callee)? else {
+            return Err(self.error("Only builtins, functions and types can be called."));
+        };
+
+        self.check_args("function", args, &params)?;
+        Ok(*ret)
+    }
+
+    // the things that can be called right now are builtins, extern functions, lambdas, module functions and types
+    // types convert a value into that type, Meters(5) wraps an int into the Meters newtype, int(distance) unwraps it again
+    fn synth_call_expr(&mut self, call_expr: &ast::CallExpr) -> Result<Type, Diagnostic> {
+        let name = match &*call_expr.callee {
+            ast::Expr::Identifier(identifier) => &identifier.symbol,
+            ast::Expr::Member(member_expr) if self.is_module(&member_expr.object) => {
+                let (module, Member::Function { params, ret }) = self.module_member(member_expr)? else {
+                    return Err(self.error(format!("'{}' is a constant and can't be called.", member_expr.property)));
+                };
+
+                return self.check_generic_args(&format!("{}.{}", module, member_expr.property), &call_expr.args, &params, &ret);
+            }
+            callee => return self.synth_value_call(callee, &call_expr.args),
+        };
+
+        if let Some(r#type) = self.synth_builtin_call(name, &call_expr.args)? {
+            return Ok(r#type);
+        }
+
+        // variables come before types, so a variable named like a type hides it when called
+        if self.find(name).is_some() {
+            let Type::Function { params, ret } = self.lookup(name)? else {
+                return Err(self.error(format!("Variable '{}' is not a function and can't be called.", name)));
+            };
+
+            self.check_args(name, &call_expr.args, &params)?;
+            return Ok(*ret);
+        }
+
+        let target = self.lookup_type(name)?;
+
+        if call_expr.args.len() != 1 {
+            return Err(self.error(format!("Converting to '{}' takes exactly 1 argument, but {} were given.", target, call_expr.args.len())));
+        }
+
+        let value = self.synth_expr(&call_expr.args[0])?;
+        let allowed = match (&target, &value) {
+            (Type::Newtype { inner, .. }, value) => **inner == *value || target == *value,
+            (target, Type::Newtype { inner, .. }) => **inner == *target,
+            (Type::Float, Type::Int) => true, // float(3) is 3.0
+            (Type::Str, value) => *value != Type::Void, // str(x) works for anything that can be printed
+            (target, value) => target == value,
+        };
+
+        if !allowed {
+            return Err(self.error(format!("Cannot convert a value of type '{}' into '{}'.", value, target)));
+        }
+
+        Ok(target)
+    }
+
+    // makes sure an expression has the type that its surroundings expect
+    // a value can be used where an optional of its type is expected, and none can be used for any optional
+    fn check_expr(&mut self, expr: &ast::Expr, expected: &Type) -> Result<(), Diagnostic> {
+        self.nest()?;
+        let checked = self.check_expr_kind(expr, expected);
+        self.depth -= 1;
+        checked
+    }
+
+    fn check_expr_kind(&mut self, expr: &ast::Expr, expected: &Type) -> Result<(), Diagnostic> {
+        // the items of literals are checked one by one, so [] and {} work wherever the type is known and [1, none] can be a list<int?>
+        match (expr, expected) {
+            (ast::Expr::NoneLiteral(_), Type::Optional(_)) => return Ok(()),
+            (ast::Expr::ListLiteral(list), Type::List(item)) => {
+                for other in &list.items {
+                    self.check_expr(other, item)?;
+                }
+                return Ok(());
+            }
+            (ast::Expr::MapLiteral(map), Type::Map(key, value)) => {
+                for (other_key, other_value) in &map.entries {
+                    self.check_expr(other_key, key)?;
+                    self.check_expr(other_value, value)?;
+                }
+                return Ok(());
+            }
+            (ast::Expr::TupleLiteral(tuple), Type::Tuple(items)) if tuple.items.len() == items.len() => {
+                for (other, item) in tuple.items.iter().zip(items) {
+                    self.check_expr(other, item)?;
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // already one level deeper for this expression
+        let found = self.synth_expr_kind(expr)?;
+        let wrapped = matches!(expected, Type::Optional(inner) if **inner == found);
+
+        if found != *expected && !wrapped {
+            return Err(self.error(format!("Expected a value of type '{}', but found a value of type '{}'.", expected, found)));
+        }
+
+        Ok(())
+    }
+
+    // validates the attributes the checker knows about and warns about the rest, returns the reason if the declaration is deprecated
+    // @deprecated or @deprecated("reason"): warns whenever the declaration is used
+    // @inline: only on constants, a hint that the generator can use the value directly instead of loading it
+    // @test: marks the declaration as a test, see Checker::tests
+    fn check_attributes(&mut self, attributes: &[ast::Attribute], ident: &str, constant: bool) -> Result<Option<String>, Diagnostic> {
+        let mut deprecated = None;
+
+        for attribute in attributes {
+            let (line, col) = (attribute.line, attribute.col);
+
+            match attribute.name.as_str() {
+                "deprecated" => {
+                    deprecated = Some(match attribute.args.as_slice() {
+                        [] => String::new(),
+                        [ast::Expr::StringLiteral(reason)] => reason.value.clone(),
+                        _ => return Err(self.error("Attribute '@deprecated' takes at most one string argument, the reason it is deprecated.").at(line, col)),
+                    });
+                }
+                "inline" | "test" if !attribute.args.is_empty() => {
+                    return Err(self.error(format!("Attribute '@{}' does not take any arguments.", attribute.name)).at(line, col));
+                }
+                "inline" if !constant => {
+                    return Err(self.error("Attribute '@inline' can only be placed on constants.").at(line, col));
+                }
+                "inline" => { /* only a hint for the generator */ }
+                "test" => self.tests.push(ident.to_string()),
+                _ => self.warnings.push(Diagnostic::warning(Stage::Checker, format!("Unknown attribute '@{}' on '{}' will be ignored.", attribute.name, ident)).at(line, col)),
+            }
+        }
+
+        Ok(deprecated)
+    }
+
+    fn check_vardecl_stmt(&mut self, vardecl: &mut ast::VariableDeclaration) -> Result<(), Diagnostic> {
+        let deprecated = self.check_attributes(&vardecl.attributes, &vardecl.identifier, vardecl.constant)?;
+
+        let r#type = match &vardecl.r#type {
+            Some(annotation) => {
+                let annotation = self.resolve_type(annotation)?;
+                self.check_expr(&vardecl.value, &annotation)?;
+                annotation
+            }
+            None => self.synth_expr(&vardecl.value)?,
+        };
+
+        if r#type == Type::Void {
+            return Err(self.error(format!("Cannot declare '{}' with a value of type 'void'.", vardecl.identifier)));
+        }
+
+        self.declare(&vardecl.identifier, r#type.clone(), deprecated);
+        self.inferred.push((vardecl.identifier.clone(), r#type.clone()));
+        vardecl.r#type = Some(r#type);
+        Ok(())
+    }
+
+    fn check_extern_fn_stmt(&mut self, extern_fn: &mut ast::ExternFunctionDeclaration) -> Result<(), Diagnostic> {
+        let deprecated = self.check_attributes(&extern_fn.attributes, &extern_fn.identifier, false)?;
+
+        // the generator keeps one table of extern functions for the whole program
+        if self.scopes.len() > 1 {
+            return Err(self.error(format!("Extern function '{}' must be declared at the top level of the program.", extern_fn.identifier)));
+        }
+
+        if BUILTINS.contains(&extern_fn.identifier.as_str()) {
+            return Err(self.error(format!("Extern function '{}' has the same name as a builtin.", extern_fn.identifier)));
+        }
+
+        if extern_fn.params.len() > u8::MAX as usize {
+            return Err(self.error(format!("Extern function '{}' can have at most {} parameters.", extern_fn.identifier, u8::MAX)));
+        }
+
+        let mut params = Vec::new();
+        for param in &mut extern_fn.params {
+            param.r#type = self.resolve_type(&param.r#type)?;
+            if param.r#type == Type::Void {
+                return Err(self.error(format!("Parameter '{}' of '{}' can't be of type 'void'.", param.name, extern_fn.identifier)));
+            }
+            params.push(param.r#type.clone());
+        }
+        extern_fn.return_type = self.resolve_type(&extern_fn.return_type)?;
+
+        let r#type = Type::Function { params, ret: Box::new(extern_fn.return_type.clone()) };
+        self.scopes.last_mut().unwrap().vars.insert(extern_fn.identifier.clone(), Binding { r#type, deprecated, native: true });
+        Ok(())
+    }
+
+    fn check_if_stmt(&mut self, if_stmt: &mut ast::IfStatement) -> Result<(), Diagnostic> {
+        self.check_expr(&if_stmt.test, &Type::Bool)?;
+        self.check_stmt(&mut if_stmt.body)?;
+
+        if let Some(alternate) = &mut if_stmt.alternate {
+            self.check_stmt(alternate)?;
+        }
+
+        Ok(())
+    }
+
+    // for x in xs, xs is a list or a generator and x is one of its items
+    fn check_for_stmt(&mut self, for_stmt: &mut ast::ForStatement) -> Result<(), Diagnostic> {
+        let item = match self.synth_expr(&for_stmt.iterable)? {
+            Type::List(item) | Type::Generator(item) => *item,
+            other => return Err(self.error(format!("Only lists and generators can be looped over, but found a value of type '{}'.", other))),
+        };
+
+        self.scopes.push(Scope::default());
+        self.declare(&for_stmt.variable, item, None);
+        self.check_stmt(&mut for_stmt.body)?;
+        self.scopes.pop();
+
+        Ok(())
+    }
+
+    fn check_yield_stmt(&mut self, yield_stmt: &ast::YieldStatement) -> Result<(), Diagnostic> {
+        let Some(values) = self.generator.clone() else {
+            return Err(self.error("yield can only be used in the body of a generator (fn(): gen<int> { yield 1 })."));
+        };
+
+        self.check_expr(&yield_stmt.value, &values)
+    }
+
+    // spawn worker(a, b), the same as a call except that nothing is given back right away
+    fn check_spawn_stmt(&mut self, spawn_stmt: &ast::SpawnStatement) -> Result<(), Diagnostic> {
+        let Type::Function { params, .. } = self.synth_expr(&spawn_stmt.function)? else {
+            return Err(self.error("Only functions can be spawned (spawn fn() { ... } or spawn worker(x))."));
+        };
+
+        self.check_args("task", &spawn_stmt.args, &params)
+    }
+
+    // defer x runs x when the block ends, the variables it uses are the ones in scope where it's written
+    // a generator can be dropped halfway through its body, which would skip what it deferred, so they can't use it
+    fn check_defer_stmt(&mut self, defer_stmt: &ast::DeferStatement) -> Result<(), Diagnostic> {
+        if self.generator.is_some() {
+            return Err(self.error("defer can't be used in the body of a generator, it would never run if the generator isn't finished."));
+        }
+
+        self.synth_expr(&defer_stmt.value)?;
+        Ok(())
+    }
+
+    fn check_block_stmt(&mut self, block_stmt: &mut ast::BlockStatement) -> Result<(), Diagnostic> {
+        self.scopes.push(Scope::default());
+
+        for stmt in &mut block_stmt.body {
+            if let ast::Stmt::DeferStatement(defer_stmt) = stmt {
+                self.check_defer_stmt(defer_stmt)?;
+                continue;
+            }
+            self.check_stmt(stmt)?;
+        }
+
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn check_stmt(&mut self, stmt: &mut ast::Stmt) -> Result<(), Diagnostic> {
+        self.nest()?;
+        let checked = self.check_stmt_kind(stmt);
+        self.depth -= 1;
+        checked
+    }
+
+    fn check_stmt_kind(&mut self, stmt: &mut ast::Stmt) -> Result<(), Diagnostic> {
+        match stmt {
+            ast::Stmt::VariableDeclaration(vardecl) => self.check_vardecl_stmt(vardecl)?,
+            ast::Stmt::TypeAlias(type_alias) => {
+                let deprecated = self.check_attributes(&type_alias.attributes, &type_alias.identifier, false)?;
+                let r#type = self.resolve_type(&type_alias.r#type)?;
+                self.declare_type(&type_alias.identifier, r#type, deprecated)?;
+            }
+            ast::Stmt::NewtypeDeclaration(newtype) => {
+                let deprecated = self.check_attributes(&newtype.attributes, &newtype.identifier, false)?;
+                let inner = self.resolve_type(&newtype.inner)?;
+                self.declare_type(&newtype.identifier, Type::Newtype { name: newtype.identifier.clone(), inner: Box::new(inner) }, deprecated)?;
+            }
+            ast::Stmt::ExternFunctionDeclaration(extern_fn) => self.check_extern_fn_stmt(extern_fn)?,
+            ast::Stmt::IfStatement(if_stmt) => self.check_if_stmt(if_stmt)?,
+            ast::Stmt::ForStatement(for_stmt) => self.check_for_stmt(for_stmt)?,
+            ast::Stmt::YieldStatement(yield_stmt) => self.check_yield_stmt(yield_stmt)?,
+            ast::Stmt::SpawnStatement(spawn_stmt) => self.check_spawn_stmt(spawn_stmt)?,
+            ast::Stmt::DeferStatement(_) => {
+                return Err(self.error("defer can only be used directly inside a block ({ defer println(1) }), it runs when the block ends."));
+            }
+            ast::Stmt::AssertStatement(assert_stmt) => {
+                self.check_expr(&assert_stmt.test, &Type::Bool)?;
+                if let Some(message) = &assert_stmt.message {
+                    self.check_expr(message, &Type::Str)?;
+                }
+            }
+            ast::Stmt::BlockStatement(block_stmt) => self.check_block_stmt(block_stmt)?,
+            ast::Stmt::Expr(expr) => {
+                self.synth_expr(expr)?;
+            }
+            ast::Stmt::Program(_) => {
+                return Err(self.error("There is a program within the program, this is not allowed!"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Type check the parser's produced AST, filling in the type of every variable declaration that didn't have an annotation.
+    /// Stops at the first error, warnings are kept in `Checker::warnings`.
+    ///
+    /// `ast`: A mutable reference to the statements of the program
+    pub fn check(&mut self, ast: &mut [ast::Stmt]) -> Result<(), Diagnostic> {
+        for stmt in ast {
+            self.check_stmt(stmt)?;
+        }
+
+        // a top level main is called once the rest of the program ran, the int it gives back is the exit code
+        if let Some(main) = self.find("main") {
+            let entry_point = Type::Function { params: Vec::new(), ret: Box::new(Type::Int) };
+            if main.native || main.r#type != entry_point {
+                return Err(self.error(format!("'main' has to be a function that takes no arguments and returns an int ({}), but it is a '{}'.", entry_point, main.r#type)));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// All types are 64-bit where it matters, same as the reserved type tokens in the lexer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Void, // what calls that don't give back a value have, like println(x)
+    Optional(Box<Type>), // str?, either a value of the inner type or none
+    List(Box<Type>), // list<str>, any amount of values of the inner type
+    Map(Box<Type>, Box<Type>), // map<str, int>, keys of the first type with values of the second, keeps the order keys were first added in
+    Tuple(Vec<Type>), // (int, str), a fixed amount of values that can each have a different type
+    Generator(Box<Type>), // gen<int>, what calling a generator gives back, values of the inner type one at a time for a for loop
+    Channel(Box<Type>), // channel<int>, how tasks give values of the inner type to each other
+    Param(&'static str), // T in the signature of a standard library function like list.map, it stands for whatever type the arguments have
+    Function { params: Vec<Type>, ret: Box<Type> }, // fn(int, str): bool, the type of extern functions and lambdas
+    Named(String), // a user declared type name as written in the source, the checker resolves it into its real type
+    Newtype { name: String, inner: Box<Type> }, // only equal to itself, even though it is stored as its inner type at runtime
+    Json, // what json.parse gives back, any JSON value, read with the json module (json.get, json.as_int, ...)
+}
+
+impl Type {
+    // maps a type keyword token to the type it names, used when parsing annotations
+    pub fn from_keyword(keyword: &str) -> Option<Type> {
+        match keyword {
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "bool" => Some(Type::Bool),
+            "str" => Some(Type::Str),
+            _ => None,
+        }
+    }
+
+    /// Whether the type mentions a type parameter (`T`) anywhere.
+    pub fn has_params(&self) -> bool {
+        match self {
+            Type::Param(_) => true,
+            Type::Optional(inner) | Type::List(inner) | Type::Generator(inner) | Type::Channel(inner) => inner.has_params(),
+            Type::Map(key, value) => key.has_params() || value.has_params(),
+            Type::Tuple(items) => items.iter().any(Type::has_params),
+            Type::Function { params, ret } => params.iter().any(Type::has_params) || ret.has_params(),
+            _ => false,
+        }
+    }
+
+    /// Replaces the type parameters that have been bound to a type, the rest are left as they are.
+    pub fn substitute(&self, bound: &HashMap<&'static str, Type>) -> Type {
+        match self {
+            Type::Param(name) => bound.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Optional(inner) => Type::Optional(Box::new(inner.substitute(bound))),
+            Type::List(inner) => Type::List(Box::new(inner.substitute(bound))),
+            Type::Generator(inner) => Type::Generator(Box::new(inner.substitute(bound))),
+            Type::Channel(inner) => Type::Channel(Box::new(inner.substitute(bound))),
+            Type::Map(key, value) => Type::Map(Box::new(key.substitute(bound)), Box::new(value.substitute(bound))),
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|item| item.substitute(bound)).collect()),
+            Type::Function { params, ret } => Type::Function {
+                params: params.iter().map(|param| param.substitute(bound)).collect(),
+                ret: Box::new(ret.substitute(bound)),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Matches a type that has type parameters against the type of a value, binding the parameters along the way.
+    /// Returns false when they don't fit, like `list<T>` and `int`, or `T` being bound to `int` and then found as `str`.
+    pub fn unify(&self, found: &Type, bound: &mut HashMap<&'static str, Type>) -> bool {
+        match (self, found) {
+            // functions that give back nothing can't stand in for fn(T): U, there would be no U to use
+            (Type::Param(_), Type::Void) => false,
+            (Type::Param(name), found) => match bound.get(name) {
+                Some(r#type) => r#type == found,
+                None => {
+                    bound.insert(name, found.clone());
+                    true
+                }
+            },
+            (Type::Optional(l), Type::Optional(r)) | (Type::List(l), Type::List(r)) | (Type::Generator(l), Type::Generator(r)) | (Type::Channel(l), Type::Channel(r)) => l.unify(r, bound),
+            (Type::Map(lk, lv), Type::Map(rk, rv)) => lk.unify(rk, bound) && lv.unify(rv, bound),
+            (Type::Tuple(l), Type::Tuple(r)) => l.len() == r.len() && l.iter().zip(r).all(|(l, r)| l.unify(r, bound)),
+            (Type::Function { params: lp, ret: lr }, Type::Function { params: rp, ret: rr }) => {
+                lp.len() == rp.len() && lp.iter().zip(rp).all(|(l, r)| l.unify(r, bound)) && lr.unify(rr, bound)
+            }
+            (expected, found) => expected == found,
+        }
+    }
+
+    /// Whether values of the type can be used as map keys, only types that compare exactly can.
+    pub fn is_key(&self) -> bool {
+        match self {
+            Type::Int | Type::Str | Type::Bool => true,
+            Type::Newtype { inner, .. } => inner.is_key(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "str"),
+            Type::Void => write!(f, "void"),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::List(inner) => write!(f, "list<{}>", inner),
+            Type::Generator(inner) => write!(f, "gen<{}>", inner),
+            Type::Channel(inner) => write!(f, "channel<{}>", inner),
+            Type::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Type::Tuple(items) => write!(f, "({})", items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(", ")),
+            Type::Param(name) => write!(f, "{}", name),
+            Type::Function { params, ret } => {
+                let params = params.iter().map(|param| param.to_string()).collect::<Vec<_>>().join(", ");
+                match &**ret {
+                    Type::Void => write!(f, "fn({})", params),
+                    ret => write!(f, "fn({}): {}", params, ret),
+                }
+            }
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Newtype { name, .. } => write!(f, "{}", name),
+            Type::Json => write!(f, "json"),
+        }
+    }
+}